@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use syslog_loose::{parse_message, Variant};
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    for variant in [Variant::Either, Variant::RFC3164, Variant::RFC5424].iter() {
+        let message = parse_message(&input, *variant);
+        // Displaying the message exercises the timestamp formatting.
+        let _ = message.to_string();
+    }
+});
@@ -0,0 +1,163 @@
+//! Parsing of Linux audit records as sent to syslog by auditd, eg.
+//! `type=SYSCALL msg=audit(1609459200.123:456): arch=c000003e syscall=59 success=yes`
+use crate::timestamp::fraction;
+use chrono::prelude::*;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till, take_till1, take_while1},
+    character::complete::{char, digit1, space0, space1},
+    combinator::{all_consuming, map_res},
+    multi::separated_list0,
+    sequence::{delimited, preceded, separated_pair, terminated, tuple},
+    IResult,
+};
+
+/// A single audit record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditRecord<'a> {
+    /// The record type, eg. `SYSCALL`.
+    pub record_type: &'a str,
+    /// The time the event was recorded.
+    pub timestamp: DateTime<FixedOffset>,
+    /// The serial number of the event. Records belonging to the same event share a serial.
+    pub serial: u64,
+    /// The remaining key-value fields. Any quotes surrounding a value are removed.
+    pub fields: Vec<(&'a str, &'a str)>,
+}
+
+/// Parse the `audit(seconds.fraction:serial)` event id.
+fn audit_id(input: &str) -> IResult<&str, (DateTime<FixedOffset>, u64)> {
+    map_res(
+        delimited(
+            tag("audit("),
+            tuple((
+                map_res(digit1, |s: &str| s.parse::<i64>()),
+                fraction,
+                preceded(char(':'), map_res(digit1, |s: &str| s.parse::<u64>())),
+            )),
+            tag("):"),
+        ),
+        |(secs, nanos, serial)| match Utc.timestamp_opt(secs, nanos) {
+            chrono::LocalResult::Single(timestamp) => Ok((timestamp.into(), serial)),
+            _ => Err("invalid audit timestamp"),
+        },
+    )(input)
+}
+
+/// Parse a field value, which may be surrounded by double or single quotes.
+fn value(input: &str) -> IResult<&str, &str> {
+    alt((
+        delimited(char('"'), take_till(|c| c == '"'), char('"')),
+        delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+        take_till(|c: char| c.is_whitespace()),
+    ))(input)
+}
+
+fn field(input: &str) -> IResult<&str, (&str, &str)> {
+    separated_pair(
+        take_till1(|c: char| c == '=' || c.is_whitespace()),
+        char('='),
+        value,
+    )(input)
+}
+
+/// Parse an audit record from the body of a syslog message.
+pub(crate) fn parse(input: &str) -> Option<AuditRecord<'_>> {
+    let (_, (record_type, (timestamp, serial), fields)) = all_consuming(tuple((
+        delimited(
+            tag("type="),
+            take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+            space1,
+        ),
+        delimited(tag("msg="), audit_id, space0),
+        terminated(separated_list0(space1, field), space0),
+    )))(input.trim())
+    .ok()?;
+
+    Some(AuditRecord {
+        record_type,
+        timestamp,
+        serial,
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_syscall() {
+        assert_eq!(
+            parse(
+                r#"type=SYSCALL msg=audit(1609459200.123:456): arch=c000003e syscall=59 success=yes comm="cat" exe="/usr/bin/cat" key=(null)"#
+            ),
+            Some(AuditRecord {
+                record_type: "SYSCALL",
+                timestamp: FixedOffset::east_opt(0)
+                    .unwrap()
+                    .with_ymd_and_hms(2021, 1, 1, 0, 0, 0)
+                    .unwrap()
+                    + chrono::Duration::milliseconds(123),
+                serial: 456,
+                fields: vec![
+                    ("arch", "c000003e"),
+                    ("syscall", "59"),
+                    ("success", "yes"),
+                    ("comm", "cat"),
+                    ("exe", "/usr/bin/cat"),
+                    ("key", "(null)"),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn parse_quoted_msg() {
+        let record = parse(
+            r#"type=USER_START msg=audit(1609459200.000:7): pid=1 msg='op=PAM:session_open acct="root" res=success'"#,
+        )
+        .unwrap();
+        assert_eq!(record.record_type, "USER_START");
+        assert_eq!(
+            record.fields,
+            vec![
+                ("pid", "1"),
+                ("msg", r#"op=PAM:session_open acct="root" res=success"#)
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_timestamp_precision() {
+        let start = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2021, 1, 1, 0, 0, 0)
+            .unwrap();
+
+        let record = parse("type=SYSCALL msg=audit(1609459200.1:1): arch=x").unwrap();
+        assert_eq!(
+            record.timestamp,
+            start + chrono::Duration::milliseconds(100)
+        );
+
+        let record = parse("type=SYSCALL msg=audit(1609459200.123456:1): arch=x").unwrap();
+        assert_eq!(
+            record.timestamp,
+            start + chrono::Duration::microseconds(123_456)
+        );
+
+        // As with other timestamps, digits beyond nanoseconds are truncated.
+        let record = parse("type=SYSCALL msg=audit(1609459200.1234567890:1): arch=x").unwrap();
+        assert_eq!(
+            record.timestamp,
+            start + chrono::Duration::nanoseconds(123_456_789)
+        );
+    }
+
+    #[test]
+    fn parse_not_audit() {
+        assert_eq!(parse("type=SYSCALL some message"), None);
+        assert_eq!(parse("a normal message"), None);
+    }
+}
@@ -0,0 +1,52 @@
+//! Parsing of messages that have been base64 encoded by the transport.
+use crate::{parse_message, Message, Variant};
+use ::base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+
+/// Standard base64, accepted with or without padding.
+const ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Parses a message that has been base64 encoded.
+///
+/// # Arguments
+///
+/// * line - the base64 encoded message.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_base64(line: &str, variant: Variant) -> Result<Message<String>, String> {
+    let decoded = ENGINE.decode(line.trim()).map_err(|err| err.to_string())?;
+    let decoded = String::from_utf8(decoded).map_err(|err| err.to_string())?;
+    Ok(parse_message(&decoded, variant).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_base64() {
+        // <34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - message
+        let line = "PDM0PjEgMjAwMy0xMC0xMVQyMjoxNDoxNS4wMDNaIG15bWFjaGluZS5leGFtcGxlLmNvbSBzdSAtIElENDcgLSBtZXNzYWdl";
+        let message = parse_message_base64(line, Variant::Either).unwrap();
+
+        assert_eq!(message.hostname.as_deref(), Some("mymachine.example.com"));
+        assert_eq!(message.appname.as_deref(), Some("su"));
+        assert_eq!(message.msgid.as_deref(), Some("ID47"));
+        assert_eq!(message.msg, "message");
+
+        // The padding is optional.
+        let message = parse_message_base64("bXNnIGY", Variant::Either).unwrap();
+        assert_eq!(message.msg, "msg f");
+        let message = parse_message_base64("bXNnIGY=", Variant::Either).unwrap();
+        assert_eq!(message.msg, "msg f");
+
+        assert!(parse_message_base64("not base64!", Variant::Either).is_err());
+        assert!(parse_message_base64("PDM0P", Variant::Either).is_err());
+    }
+}
@@ -1,8 +1,47 @@
+use nom::error::ErrorKind;
 use std::{error, fmt};
 
 /// Wrap nom errors with our own
 #[derive(Debug)]
-pub struct ParseError<'a>(pub nom::Err<(&'a str, nom::error::ErrorKind)>);
+pub struct ParseError<'a>(pub nom::Err<(&'a str, ErrorKind)>);
+
+impl<'a> ParseError<'a> {
+    /// Returns the remaining input at the point the parse failed.
+    /// Returns `None` if the parser needed more input.
+    pub fn input(&self) -> Option<&'a str> {
+        match self.0 {
+            nom::Err::Error((input, _)) | nom::Err::Failure((input, _)) => Some(input),
+            nom::Err::Incomplete(_) => None,
+        }
+    }
+
+    /// Returns the kind of parser that failed.
+    /// Returns `None` if the parser needed more input.
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self.0 {
+            nom::Err::Error((_, kind)) | nom::Err::Failure((_, kind)) => Some(kind),
+            nom::Err::Incomplete(_) => None,
+        }
+    }
+
+    /// Returns the byte offset into `message` at which the parse failed.
+    /// `message` must be the input that was parsed, otherwise `None` is returned.
+    ///
+    /// Since the parser tries several alternatives, this is the point at which the
+    /// last alternative failed, so it is only an approximate location of the problem.
+    pub fn offset(&self, message: &str) -> Option<usize> {
+        let input = self.input()?;
+        (input.as_ptr() as usize)
+            .checked_sub(message.as_ptr() as usize)
+            .filter(|offset| offset + input.len() <= message.len())
+    }
+}
+
+impl<'a> From<nom::Err<nom::error::Error<&'a str>>> for ParseError<'a> {
+    fn from(err: nom::Err<nom::error::Error<&'a str>>) -> Self {
+        ParseError(err.map(|err| (err.input, err.code)))
+    }
+}
 
 impl<'a> fmt::Display for ParseError<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -12,3 +51,21 @@ impl<'a> fmt::Display for ParseError<'a> {
 }
 
 impl<'a> error::Error for ParseError<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_offset() {
+        let message = "<34>1 bad";
+        let err = ParseError(nom::Err::Error((&message[6..], ErrorKind::Digit)));
+        assert_eq!(err.input(), Some("bad"));
+        assert_eq!(err.kind(), Some(ErrorKind::Digit));
+        assert_eq!(err.offset(message), Some(6));
+        assert_eq!(err.offset("another message"), None);
+
+        let err = ParseError(nom::Err::Incomplete(nom::Needed::Unknown));
+        assert_eq!(err.offset(message), None);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{parse_message, pri::parse_pri, Message, Variant};
+
+/// How a single message was framed, as described in
+/// [RFC6587](https://www.rfc-editor.org/rfc/rfc6587).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framing {
+    /// The message was not framed.
+    None,
+    /// The message was prefixed with its length, `<len> <message>`.
+    OctetCounting,
+    /// The message was terminated by a newline.
+    NonTransparent,
+}
+
+/// Split an octet counted frame, `<len> <message>`, off the input.
+/// The input is only taken to be octet counted if the length is followed by a priority, since
+/// an RFC5424 message without a priority starts with its version, eg. `1 2003-10-11...`.
+/// Returns the frame and the remaining input.
+fn octet_counted_frame(input: &str) -> Option<(&str, &str)> {
+    let digits = input.bytes().take_while(u8::is_ascii_digit).count();
+
+    if digits > 0 && input[digits..].starts_with(" <") {
+        let len = input[..digits].parse::<usize>().ok()?;
+        let start = digits + 1;
+        let end = start.saturating_add(len).min(input.len());
+        if input.is_char_boundary(end) {
+            return Some((&input[start..end], &input[end..]));
+        }
+    }
+
+    None
+}
+
+/// Strip any framing from a single message, returning the message and the framing that was used.
+pub(crate) fn unframe(input: &str) -> (&str, Framing) {
+    if let Some((frame, _)) = octet_counted_frame(input) {
+        return (frame, Framing::OctetCounting);
+    }
+
+    match input.strip_suffix('\n') {
+        Some(message) => (
+            message.strip_suffix('\r').unwrap_or(message),
+            Framing::NonTransparent,
+        ),
+        None => (input, Framing::None),
+    }
+}
+
+/// Split the next frame off the input.
+/// If the frame starts with a length followed by a priority it is taken to be octet counted,
+/// `<len> <message>`, otherwise the frame runs until the next newline.
+/// Returns the frame and the remaining input.
+pub(crate) fn next_frame(input: &str) -> (&str, &str) {
+    if let Some(frame) = octet_counted_frame(input) {
+        return frame;
+    }
+
+    match input.find('\n') {
+        Some(idx) => (&input[..idx], &input[idx + 1..]),
+        None => (input, ""),
+    }
+}
+
+/// Parses a buffer containing multiple messages framed as per
+/// [RFC6587](https://www.rfc-editor.org/rfc/rfc6587), as used when syslog is sent over TCP.
+/// Each frame can either be octet counted, `<len> <message>`, or terminated by a newline.
+/// Blank lines between frames are skipped.
+///
+/// # Arguments
+///
+/// * input - the string containing the frames.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_frames(input: &str, variant: Variant) -> impl Iterator<Item = Message<&str>> {
+    let mut remaining = input;
+
+    std::iter::from_fn(move || loop {
+        remaining = remaining.trim_start_matches(['\r', '\n']);
+        if remaining.is_empty() {
+            return None;
+        }
+
+        let (frame, rest) = next_frame(remaining);
+        remaining = rest;
+
+        if !frame.trim().is_empty() {
+            return Some(parse_message(frame, variant));
+        }
+    })
+}
+
+/// Split the next message off the input. The message runs until the next line that starts
+/// with a priority, so a message spanning several lines stays in one piece.
+/// Returns the message and the remaining input.
+fn next_message(input: &str) -> (&str, &str) {
+    input
+        .match_indices('\n')
+        .find(|(idx, _)| matches!(parse_pri(&input[idx + 1..]), Ok((_, Some(_)))))
+        .map(|(idx, _)| (&input[..idx], &input[idx + 1..]))
+        .unwrap_or((input, ""))
+}
+
+/// Parses a buffer containing multiple messages, each starting on a new line.
+/// A new message is only started when a line starts with a priority, eg. `<34>`,
+/// so any lines in between are kept as part of the previous message.
+///
+/// # Arguments
+///
+/// * input - the string containing the messages.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_messages(input: &str, variant: Variant) -> Vec<Message<&str>> {
+    let mut messages = Vec::new();
+    let mut remaining = input;
+
+    while !remaining.trim().is_empty() {
+        let (message, rest) = next_message(remaining);
+        if !message.trim().is_empty() {
+            messages.push(parse_message(message, variant));
+        }
+        remaining = rest;
+    }
+
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn octet_counted_frames() {
+        let msg1 = "<34>1 2003-10-11T22:14:15.003Z host app - - - first\nline";
+        let msg2 = "<34>1 2003-10-11T22:14:15.003Z host app - - - second";
+        let input = format!("{} {}{} {}", msg1.len(), msg1, msg2.len(), msg2);
+
+        let msgs = parse_frames(&input, Variant::Either)
+            .map(|message| message.msg)
+            .collect::<Vec<_>>();
+        assert_eq!(msgs, vec!["first\nline", "second"]);
+    }
+
+    #[test]
+    fn newline_frames() {
+        let input = "<34>1 2003-10-11T22:14:15.003Z host app - - - first\n\n<34>1 2003-10-11T22:14:15.003Z host app - - - second\n";
+
+        let msgs = parse_frames(input, Variant::Either)
+            .map(|message| message.msg)
+            .collect::<Vec<_>>();
+        assert_eq!(msgs, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn newline_frames_without_pri() {
+        let input = "1 2003-10-11T22:14:15.003Z host app - - - first\n1 2003-10-11T22:14:15.003Z host app - - - second\n";
+
+        let msgs = parse_frames(input, Variant::Either)
+            .map(|message| (message.hostname, message.msg))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            msgs,
+            vec![(Some("host"), "first"), (Some("host"), "second")]
+        );
+    }
+
+    #[test]
+    fn truncated_octet_count() {
+        let (frame, rest) = next_frame("100 <34>1 - host app - - - short");
+        assert_eq!(frame, "<34>1 - host app - - - short");
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn unframe_message() {
+        assert_eq!(unframe("7 <34>msg"), ("<34>msg", Framing::OctetCounting));
+        assert_eq!(unframe("<34>msg\r\n"), ("<34>msg", Framing::NonTransparent));
+        assert_eq!(unframe("<34>msg"), ("<34>msg", Framing::None));
+        assert_eq!(
+            unframe("1 2003-10-11T22:14:15.003Z host app - - - msg"),
+            (
+                "1 2003-10-11T22:14:15.003Z host app - - - msg",
+                Framing::None
+            )
+        );
+    }
+
+    #[test]
+    fn multiline_messages() {
+        let input = "<34>1 2003-10-11T22:14:15.003Z host app - - - first\nline\n<34>1 2003-10-11T22:14:15.003Z host app - - - second\n";
+
+        let msgs = parse_messages(input, Variant::Either)
+            .into_iter()
+            .map(|message| message.msg)
+            .collect::<Vec<_>>();
+        assert_eq!(msgs, vec!["first\nline", "second"]);
+    }
+}
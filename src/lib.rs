@@ -1,10 +1,16 @@
 #![deny(clippy::all)]
 #![deny(clippy::cargo)]
+#![allow(clippy::multiple_crate_versions)]
 extern crate nom;
 
+mod auditd;
+#[cfg(feature = "base64")]
+mod base64;
 mod error;
+mod framing;
 mod message;
-mod parsers;
+mod options;
+pub mod parsers;
 mod pri;
 mod procid;
 mod rfc3164;
@@ -14,17 +20,29 @@ mod timestamp;
 
 use chrono::prelude::*;
 use nom::{branch::alt, IResult};
+use std::borrow::Cow;
 
-pub use message::{Message, Protocol};
-pub use pri::{decompose_pri, SyslogFacility, SyslogSeverity};
+pub use auditd::AuditRecord;
+#[cfg(feature = "base64")]
+pub use base64::parse_message_base64;
+pub use error::ParseError;
+pub use framing::{parse_frames, parse_messages, Framing};
+pub use message::{
+    BytesMessage, Message, MessageBuilder, MessageParts, ParseMetadata, ParseStats, Protocol,
+};
+pub use options::{HeaderBoundary, ParseOptions};
+pub use pri::{decompose_pri, FacilityMap, SyslogFacility, SyslogSeverity};
 pub use procid::ProcId;
-pub use structured_data::StructuredElement;
+pub use structured_data::{
+    escape_param_value, Origin, StructuredElement, StructuredElementBuilder, TimeQuality,
+};
 pub use timestamp::IncompleteDate;
 
 /// Used to specify which variant of the RFC message we are expecting.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Variant {
     /// Either variant. First attempt to parse as RFC5424, if that fails try RFC3164.
+    #[default]
     Either,
     /// Parse as [RFC3164](https://www.rfc-editor.org/rfc/rfc3164)
     RFC3164,
@@ -38,17 +56,23 @@ fn parse<F, Tz: TimeZone + Copy>(
     get_year: F,
     tz: Option<Tz>,
     variant: Variant,
+    options: ParseOptions,
 ) -> IResult<&str, Message<&str>>
 where
     F: FnOnce(IncompleteDate) -> i32 + Copy,
 {
-    match variant {
-        Variant::Either => {
-            alt((rfc5424::parse, |input| rfc3164::parse(input, get_year, tz)))(input.trim())
-        }
-        Variant::RFC3164 => rfc3164::parse(input.trim(), get_year, tz),
-        Variant::RFC5424 => rfc5424::parse(input.trim()),
-    }
+    let input = if options.no_trim { input } else { input.trim() };
+
+    let (rest, message) = match variant {
+        Variant::Either => alt((
+            |input| rfc5424::parse(input, options),
+            |input| rfc3164::parse(input, get_year, tz, options),
+        ))(input),
+        Variant::RFC3164 => rfc3164::parse(input, get_year, tz, options),
+        Variant::RFC5424 => rfc5424::parse(input, options),
+    }?;
+
+    Ok((rest, options.normalize_borrowed(message)))
 }
 
 ///
@@ -59,7 +83,7 @@ where
 /// * input - the string containing the message.
 /// * tz - a default timezone to use if the parsed timestamp does not specify one
 /// * get_year - a function that is called if the parsed message contains a date with no year.
-///              the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
 /// * variant - the variant of message we are expecting to receive.
 ///
 pub fn parse_message_with_year_tz<F, Tz: TimeZone + Copy>(
@@ -72,24 +96,82 @@ where
     F: FnOnce(IncompleteDate) -> i32 + Copy,
     DateTime<FixedOffset>: From<DateTime<Tz>>,
 {
-    parse(input, get_year, tz, variant)
+    parse_message_with_year_tz_options(input, get_year, tz, variant, ParseOptions::default())
+}
+
+///
+/// Parse the message, passing the timezone to the function that resolves the year.
+/// This allows the year to be resolved in the same timezone the date will be parsed in,
+/// avoiding off by one year errors around new year when the local and UTC dates disagree.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * tz - a default timezone to use if the parsed timestamp does not specify one
+/// * get_year - a function that is called if the parsed message contains a date with no year.
+///   the function takes a (month, date, hour, minute, second) tuple along with the `tz`
+///   and should return the year to use. A `tz` of `None` means the date is in local time.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_with_year_tz_ctx<F, Tz: TimeZone + Copy>(
+    input: &str,
+    get_year: F,
+    tz: Option<Tz>,
+    variant: Variant,
+) -> Message<&str>
+where
+    F: FnOnce(IncompleteDate, Option<Tz>) -> i32 + Copy,
+    DateTime<FixedOffset>: From<DateTime<Tz>>,
+{
+    parse_message_with_year_tz(input, move |date| get_year(date, tz), tz, variant)
+}
+
+///
+/// Parse the message using the given options to control how loosely it is parsed.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * tz - a default timezone to use if the parsed timestamp does not specify one
+/// * get_year - a function that is called if the parsed message contains a date with no year.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+/// * variant - the variant of message we are expecting to receive.
+/// * options - options that tweak the behaviour of the parser.
+///
+pub fn parse_message_with_year_tz_options<F, Tz: TimeZone + Copy>(
+    input: &str,
+    get_year: F,
+    tz: Option<Tz>,
+    variant: Variant,
+    options: ParseOptions,
+) -> Message<&str>
+where
+    F: FnOnce(IncompleteDate) -> i32 + Copy,
+    DateTime<FixedOffset>: From<DateTime<Tz>>,
+{
+    parse(input, get_year, tz, variant, options)
         .map(|(_, result)| result)
-        .unwrap_or(
-            // If we fail to parse, the entire input becomes the message
-            // the rest of the fields are empty.
-            Message {
-                facility: None,
-                severity: None,
-                timestamp: None,
-                hostname: None,
-                appname: None,
-                procid: None,
-                msgid: None,
-                protocol: Protocol::RFC3164,
-                structured_data: vec![],
-                msg: input,
-            },
-        )
+        .unwrap_or_else(|_| unparsed_message(input))
+}
+
+/// If we fail to parse, the entire input becomes the message
+/// the rest of the fields are empty.
+fn unparsed_message(input: &str) -> Message<&str> {
+    Message {
+        facility: None,
+        severity: None,
+        timestamp: None,
+        raw_timestamp: None,
+        hostname: None,
+        appname: None,
+        procid: None,
+        msgid: None,
+        protocol: Protocol::RFC3164,
+        structured_data: vec![],
+        unparsed_structured_data: vec![],
+        had_bom: false,
+        msg: input,
+    }
 }
 
 ///
@@ -99,7 +181,7 @@ where
 ///
 /// * input - the string containing the message.
 /// * get_year - a function that is called if the parsed message contains a date with no year.
-///              the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
 /// * variant - the variant of message we are expecting to receive.
 ///
 pub fn parse_message_with_year<F>(input: &str, get_year: F, variant: Variant) -> Message<&str>
@@ -122,6 +204,361 @@ pub fn parse_message(input: &str, variant: Variant) -> Message<&str> {
     parse_message_with_year(input, |_| Local::now().year(), variant)
 }
 
+/// Parses the message, guarding against giant inputs.
+/// If the input is longer than `max_len` bytes no attempt is made to parse it,
+/// the entire input becomes the message and the rest of the fields are empty.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+/// * max_len - the maximum length of input to parse, or `None` for no limit.
+///
+pub fn parse_message_with_limits(
+    input: &str,
+    variant: Variant,
+    max_len: Option<usize>,
+) -> Message<&str> {
+    match max_len {
+        Some(max_len) if input.len() > max_len => unparsed_message(input),
+        _ => parse_message(input, variant),
+    }
+}
+
+/// Parses the first message in the input, also returning the number of bytes of the input
+/// that were consumed, so a caller parsing from its own buffer knows how far to advance.
+/// The message ends at the end of its frame, either the next newline or the length given
+/// by octet counting, `<len> <message>`. Any terminating newline is counted as consumed.
+/// If the message fails to parse the whole frame becomes the message.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_with_consumed(input: &str, variant: Variant) -> (Message<&str>, usize) {
+    let (frame, rest) = framing::next_frame(input);
+    (parse_message(frame, variant), input.len() - rest.len())
+}
+
+/// Parses the message, also returning statistics about the parse, such as how many
+/// malformed structured data elements were dropped. Useful for spotting misbehaving senders.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_with_stats(input: &str, variant: Variant) -> (Message<&str>, ParseStats) {
+    let message = parse_message(input, variant);
+    let stats = ParseStats {
+        dropped_structured_data: message.unparsed_structured_data.len(),
+    };
+
+    (message, stats)
+}
+
+/// Parses the message, also returning metadata describing how it was parsed: the variant
+/// that matched, whether it had a priority, whether parsing failed so the whole input became
+/// the message, and the framing it was sent with. Any framing is removed before parsing.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_with_metadata(
+    input: &str,
+    variant: Variant,
+) -> (Message<&str>, ParseMetadata) {
+    let (input, framing) = framing::unframe(input);
+    let (message, fallback) = match parse::<_, Local>(
+        input,
+        |_| Local::now().year(),
+        None,
+        variant,
+        ParseOptions::default(),
+    ) {
+        Ok((_, message)) => (message, false),
+        Err(_) => (unparsed_message(input), true),
+    };
+
+    let metadata = ParseMetadata {
+        variant: match message.protocol {
+            Protocol::RFC3164 => Variant::RFC3164,
+            Protocol::RFC5424(_) => Variant::RFC5424,
+        },
+        pri_present: matches!(pri::parse_pri(input.trim_start()), Ok((_, Some(_)))),
+        fallback,
+        framing,
+    };
+
+    (message, metadata)
+}
+
+/// Parses the message only if its facility and severity pass the predicate.
+/// Just the priority is parsed before calling the predicate, so messages that aren't wanted,
+/// eg. debug messages, can be discarded cheaply. If the predicate returns false `None` is
+/// returned without parsing the rest of the message.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+/// * predicate - called with the facility and severity, returns true if the message should be parsed.
+///
+pub fn parse_message_if<P>(input: &str, variant: Variant, predicate: P) -> Option<Message<&str>>
+where
+    P: Fn(Option<SyslogFacility>, Option<SyslogSeverity>) -> bool,
+{
+    let (facility, severity) = pri::pri(ParseOptions::default())(input.trim())
+        .map(|(_, pri)| pri)
+        .unwrap_or((None, None));
+
+    if predicate(facility, severity) {
+        Some(parse_message(input, variant))
+    } else {
+        None
+    }
+}
+
+/// Parses the message without first trimming whitespace from the start and end of the input,
+/// so any trailing whitespace, such as the newline written by rsyslog file templates,
+/// is kept in the message body.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_no_trim(input: &str, variant: Variant) -> Message<&str> {
+    parse_message_with_options(
+        input,
+        variant,
+        ParseOptions {
+            no_trim: true,
+            ..Default::default()
+        },
+    )
+}
+
+/// Parses the message with everything that controls the parse, including the variant,
+/// timezone and year, taken from the options. If it can't be parsed, or is longer than
+/// `options.max_len`, an error is returned.
+/// The returned message borrows from the input, so `lowercase_hostname`, which needs to
+/// allocate, is not applied. Use `parse_message_with_options_owned` to apply it.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * options - options that control the behaviour of the parser.
+///
+pub fn parse_with_options<'a>(
+    input: &'a str,
+    options: &ParseOptions,
+) -> Result<Message<&'a str>, ParseError<'a>> {
+    if options.max_len.is_some_and(|max_len| input.len() > max_len) {
+        return Err(ParseError(nom::Err::Failure((
+            input,
+            nom::error::ErrorKind::TooLarge,
+        ))));
+    }
+
+    let get_year = options.get_year.unwrap_or(|_| Local::now().year());
+    parse(input, get_year, options.timezone, options.variant, *options)
+        .map(|(_, result)| result)
+        .map_err(ParseError::from)
+}
+
+/// Parses the message using the given options to control how loosely it is parsed.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+/// * options - options that tweak the behaviour of the parser.
+///
+pub fn parse_message_with_options(
+    input: &str,
+    variant: Variant,
+    options: ParseOptions,
+) -> Message<&str> {
+    parse_message_with_year_tz_options::<_, Local>(
+        input,
+        |_| Local::now().year(),
+        None,
+        variant,
+        options,
+    )
+}
+
+/// Convert an offset into the lossily decoded string back to an offset into the raw bytes.
+/// Each invalid sequence in the raw bytes is replaced with a single `U+FFFD` in the string.
+fn raw_offset(raw: &[u8], offset: usize) -> usize {
+    let mut raw_pos = 0;
+    let mut pos = 0;
+    for chunk in raw.utf8_chunks() {
+        let valid = chunk.valid().len();
+        if pos + valid >= offset {
+            return raw_pos + offset - pos;
+        }
+        raw_pos += valid + chunk.invalid().len();
+        pos += valid + char::REPLACEMENT_CHARACTER.len_utf8();
+    }
+    raw_pos
+}
+
+/// Find the raw bytes of the msg, given the msg as a slice of the lossily decoded input.
+fn raw_msg<'a>(raw: &'a [u8], decoded: &str, msg: &str) -> &'a [u8] {
+    let start = (msg.as_ptr() as usize).wrapping_sub(decoded.as_ptr() as usize);
+    if start > decoded.len() {
+        // The msg doesn't point into the input, so must be empty.
+        return &[];
+    }
+
+    &raw[raw_offset(raw, start)..raw_offset(raw, start + msg.len())]
+}
+
+/// Parses the message from raw bytes.
+/// The header of a syslog message is always ASCII, but the rest of the message may not be valid
+/// UTF-8. Rather than rejecting the whole message, any invalid UTF-8 sequences are replaced with
+/// `U+FFFD REPLACEMENT CHARACTER` as per `String::from_utf8_lossy`.
+/// If the input is valid UTF-8 the returned message borrows from the input.
+/// The raw bytes of the msg are kept, see `BytesMessage::msg_lossy_latin1`.
+///
+/// # Arguments
+///
+/// * input - the bytes containing the message.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_bytes(input: &[u8], variant: Variant) -> BytesMessage<'_> {
+    match String::from_utf8_lossy(input) {
+        Cow::Borrowed(decoded) => {
+            let message = parse_message(decoded, variant);
+            BytesMessage {
+                raw_msg: raw_msg(input, decoded, message.msg),
+                message: message.map_str(Cow::Borrowed),
+            }
+        }
+        Cow::Owned(decoded) => {
+            let message = parse_message(&decoded, variant);
+            BytesMessage {
+                raw_msg: raw_msg(input, &decoded, message.msg),
+                message: message.map_str(|s| Cow::Owned(s.to_string())),
+            }
+        }
+    }
+}
+
+/// Parses the message using the given options, returning an owned message.
+/// Options that rewrite the parsed fields, such as `lowercase_hostname`, are only
+/// applied by this function since they require allocating new strings.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+/// * options - options that tweak the behaviour of the parser.
+///
+pub fn parse_message_with_options_owned(
+    input: &str,
+    variant: Variant,
+    options: ParseOptions,
+) -> Message<String> {
+    options.normalize(parse_message_with_options(input, variant, options).into())
+}
+
+/// Parses the message, returning an owned message that doesn't borrow from the input.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_owned(input: &str, variant: Variant) -> Message<String> {
+    parse_message(input, variant).into()
+}
+
+/// Parses the message, returning an owned message that doesn't borrow from the input.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * get_year - a function that is called if the parsed message contains a date with no year.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_with_year_owned<F>(
+    input: &str,
+    get_year: F,
+    variant: Variant,
+) -> Message<String>
+where
+    F: FnOnce(IncompleteDate) -> i32 + Copy,
+{
+    parse_message_with_year(input, get_year, variant).into()
+}
+
+/// Parses the message, returning an owned message that doesn't borrow from the input.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * get_year - a function that is called if the parsed message contains a date with no year.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+/// * tz - a default timezone to use if the parsed timestamp does not specify one
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_with_year_tz_owned<F, Tz: TimeZone + Copy>(
+    input: &str,
+    get_year: F,
+    tz: Option<Tz>,
+    variant: Variant,
+) -> Message<String>
+where
+    F: FnOnce(IncompleteDate) -> i32 + Copy,
+    DateTime<FixedOffset>: From<DateTime<Tz>>,
+{
+    parse_message_with_year_tz(input, get_year, tz, variant).into()
+}
+
+/// Parses the message, requiring that it contains structured data.
+/// If the message has no structured data elements an Error is returned.
+/// For messages where the timestamp doesn't specify a year it just
+/// takes the current year.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_require_sd(input: &str, variant: Variant) -> Result<Message<&str>, String> {
+    let message = parse_message(input, variant);
+    if message.is_structured() {
+        Ok(message)
+    } else {
+        Err("message does not contain any structured data".to_string())
+    }
+}
+
 ///
 /// Parse the message exactly. If it can't be parsed, an Error is returned.
 /// Note, since it is hard to locate exactly what is causing the error due to the parser trying
@@ -131,7 +568,7 @@ pub fn parse_message(input: &str, variant: Variant) -> Message<&str> {
 ///
 /// * input - the string containing the message.
 /// * get_year - a function that is called if the parsed message contains a date with no year.
-///              the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
 /// * variant - the variant of message we are expecting to receive.
 ///
 pub fn parse_message_with_year_exact<F>(
@@ -142,11 +579,36 @@ pub fn parse_message_with_year_exact<F>(
 where
     F: FnOnce(IncompleteDate) -> i32 + Copy,
 {
-    parse::<_, Local>(input, get_year, None, variant)
+    parse::<_, Local>(input, get_year, None, variant, ParseOptions::default())
         .map(|(_, result)| result)
         .map_err(|_| "unable to parse input as valid syslog message".to_string())
 }
 
+///
+/// Parse the message exactly. If it can't be parsed, a `ParseError` is returned holding the
+/// remaining input at the point parsing failed, from which the offset of the failure can be found.
+/// Since the parser tries so many different combinations the location is only approximate.
+///
+/// # Arguments
+///
+/// * input - the string containing the message.
+/// * get_year - a function that is called if the parsed message contains a date with no year.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+/// * variant - the variant of message we are expecting to receive.
+///
+pub fn parse_message_with_year_exact_detailed<F>(
+    input: &str,
+    get_year: F,
+    variant: Variant,
+) -> Result<Message<&str>, ParseError<'_>>
+where
+    F: FnOnce(IncompleteDate) -> i32 + Copy,
+{
+    parse::<_, Local>(input, get_year, None, variant, ParseOptions::default())
+        .map(|(_, result)| result)
+        .map_err(ParseError::from)
+}
+
 ///
 /// Parse the message exactly. If it can't be parsed, an Error is returned.
 /// Note, since it is hard to locate exactly what is causing the error due to the parser trying
@@ -157,7 +619,7 @@ where
 /// * input - the string containing the message.
 /// * tz - a default timezone to use if the parsed timestamp does not specify one
 /// * get_year - a function that is called if the parsed message contains a date with no year.
-///              the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
 /// * variant - the variant of message we are expecting to receive.
 ///
 pub fn parse_message_with_year_exact_tz<F, Tz: TimeZone + Copy>(
@@ -169,7 +631,7 @@ pub fn parse_message_with_year_exact_tz<F, Tz: TimeZone + Copy>(
 where
     F: FnOnce(IncompleteDate) -> i32 + Copy,
 {
-    parse(input, get_year, tz, variant)
+    parse(input, get_year, tz, variant, ParseOptions::default())
         .map(|(_, result)| result)
         .map_err(|_| "unable to parse input as valid syslog message".to_string())
 }
@@ -1,49 +1,99 @@
+use crate::auditd::{self, AuditRecord};
+use crate::framing::Framing;
 use crate::pri::{compose_pri, SyslogFacility, SyslogSeverity};
 use crate::procid::ProcId;
 use crate::structured_data;
+use crate::Variant;
 use chrono::prelude::*;
-use std::fmt;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr},
+    ops::Deref,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::upper_case_acronyms)]
 pub enum Protocol {
     RFC3164,
     RFC5424(u32),
 }
 
+impl Protocol {
+    /// Returns the version number of an RFC5424 message, or `None` for RFC3164.
+    pub fn version(&self) -> Option<u32> {
+        match self {
+            Protocol::RFC3164 => None,
+            Protocol::RFC5424(version) => Some(*version),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message<S: AsRef<str> + Ord + PartialEq + Clone> {
     pub protocol: Protocol,
     pub facility: Option<SyslogFacility>,
     pub severity: Option<SyslogSeverity>,
     pub timestamp: Option<DateTime<FixedOffset>>,
+    /// The timestamp exactly as it was written in the message, eg. `2019-02-13T21:53:30.605850+00:00`.
+    /// This is not used when comparing or displaying messages.
+    pub raw_timestamp: Option<S>,
+    /// The hostname as it was written. A relay can record it as `ip/hostname`, eg.
+    /// `192.0.2.1/host`, which is kept whole here since the field borrows from the input
+    /// and so can't also hold the ip. Use `relay_hostname` and `relay_ip` to split it.
     pub hostname: Option<S>,
     pub appname: Option<S>,
     pub procid: Option<ProcId<S>>,
     pub msgid: Option<S>,
     pub structured_data: Vec<structured_data::StructuredElement<S>>,
+    /// The raw text of any structured data elements that could not be parsed,
+    /// eg. `[incorrect x]`. These are not written out when displaying the message.
+    pub unparsed_structured_data: Vec<S>,
+    /// True if the message body started with a UTF-8 byte order mark, as RFC5424 allows.
+    /// This is also detected in RFC3164 messages.
+    /// The BOM is removed from `msg` unless the `keep_bom` option is set.
+    /// This is not used when comparing messages.
+    pub had_bom: bool,
     pub msg: S,
 }
 
-impl<S: AsRef<str> + Ord + PartialEq + Clone> fmt::Display for Message<S> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let empty = "-".to_string();
+impl<S: AsRef<str> + Ord + PartialEq + Clone> Message<S> {
+    /// Write the message in the same format as `Display`, but with the fractional seconds
+    /// of the timestamp written with the given precision, eg. `SecondsFormat::Millis`.
+    pub fn write_with_precision<W: fmt::Write>(
+        &self,
+        f: &mut W,
+        precision: SecondsFormat,
+    ) -> fmt::Result {
+        let empty = "-";
 
         write!(
             f,
-            "<{}>{} {} {} ",
+            "<{}>",
             compose_pri(
                 self.facility.unwrap_or(SyslogFacility::LOG_SYSLOG),
                 self.severity.unwrap_or(SyslogSeverity::SEV_DEBUG)
-            ),
-            match self.protocol {
-                Protocol::RFC3164 => "".to_string(),
-                Protocol::RFC5424(version) => version.to_string(),
-            },
-            self.timestamp
-                .unwrap_or_else(|| Utc::now().into())
-                .to_rfc3339(),
-            self.hostname.as_ref().map(|s| s.as_ref()).unwrap_or(&empty)
+            )
+        )?;
+
+        if let Protocol::RFC5424(version) = self.protocol {
+            write!(f, "{}", version)?;
+        }
+
+        // A missing timestamp is written as the NILVALUE in RFC5424, and left out in RFC3164.
+        match (&self.protocol, self.timestamp) {
+            (_, Some(timestamp)) => write!(f, " {} ", timestamp.to_rfc3339_opts(precision, false))?,
+            (Protocol::RFC5424(_), None) => write!(f, " {} ", empty)?,
+            (Protocol::RFC3164, None) => {}
+        }
+
+        write!(
+            f,
+            "{} ",
+            self.hostname.as_ref().map(|s| s.as_ref()).unwrap_or(empty)
         )?;
 
         match self.protocol {
@@ -51,7 +101,7 @@ impl<S: AsRef<str> + Ord + PartialEq + Clone> fmt::Display for Message<S> {
                 write!(
                     f,
                     "{} ",
-                    self.appname.as_ref().map(|s| s.as_ref()).unwrap_or(&empty)
+                    self.appname.as_ref().map(|s| s.as_ref()).unwrap_or(empty)
                 )?;
                 match &self.procid {
                     None => write!(f, "- ")?,
@@ -69,7 +119,7 @@ impl<S: AsRef<str> + Ord + PartialEq + Clone> fmt::Display for Message<S> {
             write!(
                 f,
                 "{} ",
-                self.msgid.as_ref().map(|s| s.as_ref()).unwrap_or(&empty)
+                self.msgid.as_ref().map(|s| s.as_ref()).unwrap_or(empty)
             )?;
         }
 
@@ -88,6 +138,13 @@ impl<S: AsRef<str> + Ord + PartialEq + Clone> fmt::Display for Message<S> {
     }
 }
 
+impl<S: AsRef<str> + Ord + PartialEq + Clone> fmt::Display for Message<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Write as many fractional digits as needed so no sub-second precision is lost.
+        self.write_with_precision(f, SecondsFormat::AutoSi)
+    }
+}
+
 impl<S: AsRef<str> + Ord + Clone> PartialEq for Message<S> {
     fn eq(&self, other: &Self) -> bool {
         self.facility == other.facility
@@ -98,16 +155,696 @@ impl<S: AsRef<str> + Ord + Clone> PartialEq for Message<S> {
             && self.procid == other.procid
             && self.msgid == other.msgid
             && self.structured_data == other.structured_data
+            && self.unparsed_structured_data == other.unparsed_structured_data
+            && self.msg == other.msg
+    }
+}
+
+/// Parse a token from the message body as an ip address, ignoring any trailing punctuation
+/// and an ipv4 port.
+fn parse_ip_token(token: &str) -> Option<IpAddr> {
+    let token = token.trim_end_matches(['.', ':']);
+    token.parse().ok().or_else(|| {
+        let (ip, port) = token.rsplit_once(':')?;
+        port.parse::<u16>().ok()?;
+        ip.parse::<Ipv4Addr>().ok().map(IpAddr::V4)
+    })
+}
+
+/// A 64 bit FNV-1a hash, used for fingerprints since, unlike `DefaultHasher`,
+/// the algorithm is fixed.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+
+    /// Strings are written as their length followed by their bytes.
+    fn write_str(&mut self, value: &str) {
+        self.write_len(value.len());
+        self.write(value.as_bytes());
+    }
+
+    /// A missing value is written as a 0, and a present one as a 1 followed by the value.
+    fn write_opt_str(&mut self, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                self.write(&[1]);
+                self.write_str(value);
+            }
+            None => self.write(&[0]),
+        }
+    }
+
+    fn write_opt_code(&mut self, code: Option<u8>) {
+        match code {
+            Some(code) => self.write(&[1, code]),
+            None => self.write(&[0]),
+        }
+    }
+}
+
+/// Split a hostname of the form `ip/hostname` into the ip and the hostname.
+fn split_relay_hostname(hostname: &str) -> Option<(IpAddr, &str)> {
+    let (ip, hostname) = hostname.split_once('/')?;
+    Some((ip.parse().ok()?, hostname))
+}
+
+impl<S: AsRef<str> + Ord + Clone> Message<S> {
+    /// Returns a builder for an RFC5424 message with the given body.
+    /// Any fields that aren't set are left empty.
+    pub fn builder(msg: S) -> MessageBuilder<S> {
+        MessageBuilder {
+            message: Message {
+                protocol: Protocol::RFC5424(1),
+                facility: None,
+                severity: None,
+                timestamp: None,
+                raw_timestamp: None,
+                hostname: None,
+                appname: None,
+                procid: None,
+                msgid: None,
+                structured_data: vec![],
+                unparsed_structured_data: vec![],
+                had_bom: false,
+                msg,
+            },
+        }
+    }
+
+    /// Converts the timestamp to the given timezone, eg. `Utc`, keeping the same instant in time.
+    /// Unlike the `tz` passed when parsing, which is only used when the message doesn't specify
+    /// an offset, this changes the offset of every timestamp. A message with no timestamp is
+    /// left untouched.
+    pub fn with_timezone<Tz: TimeZone>(mut self, tz: Tz) -> Self {
+        self.timestamp = self.timestamp.map(|timestamp| {
+            let offset = tz.offset_from_utc_datetime(&timestamp.naive_utc()).fix();
+            timestamp.with_timezone(&offset)
+        });
+        self
+    }
+
+    /// Splits the message into its parts, so the fields can be moved out without cloning.
+    pub fn into_parts(self) -> MessageParts<S> {
+        MessageParts {
+            protocol: self.protocol,
+            facility: self.facility,
+            severity: self.severity,
+            timestamp: self.timestamp,
+            raw_timestamp: self.raw_timestamp,
+            hostname: self.hostname,
+            appname: self.appname,
+            procid: self.procid,
+            msgid: self.msgid,
+            structured_data: self.structured_data,
+            unparsed_structured_data: self.unparsed_structured_data,
+            had_bom: self.had_bom,
+            msg: self.msg,
+        }
+    }
+
+    /// Returns an owned copy of the structured data, without needing to convert
+    /// the whole message.
+    pub fn structured_data_owned(&self) -> Vec<structured_data::StructuredElement<String>> {
+        self.structured_data
+            .iter()
+            .map(|element| structured_data::StructuredElement {
+                id: element.id.as_ref().to_string(),
+                params: element
+                    .params
+                    .iter()
+                    .map(|(name, value)| (name.as_ref().to_string(), value.as_ref().to_string()))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Flattens all the structured data into a single map keyed by `(element id, param name)`,
+    /// with the escapes stripped from the values.
+    /// If the same param appears more than once, eg. in two elements with the same id, the
+    /// last value wins.
+    pub fn params_map(&self) -> BTreeMap<(S, S), String> {
+        self.structured_data
+            .iter()
+            .flat_map(|element| {
+                element
+                    .params()
+                    .map(move |(name, value)| ((element.id.clone(), name.clone()), value))
+            })
+            .collect()
+    }
+
+    /// Returns the `sysUpTime` param of the `meta` structured data element.
+    /// The value is returned as it was sent. RFC5424 specifies hundredths of a second,
+    /// but some senders use seconds.
+    pub fn sys_uptime(&self) -> Option<u64> {
+        self.structured_data
+            .iter()
+            .filter(|element| element.id.as_ref() == "meta")
+            .find_map(|element| element.get("sysUpTime"))
+            .and_then(|uptime| uptime.parse().ok())
+    }
+
+    /// Returns the `sequenceId` param of the `meta` structured data element, as sent by
+    /// syslog-ng and rsyslog. Returns `None` if it is missing or isn't a number.
+    pub fn sequence_id(&self) -> Option<u64> {
+        self.structured_data
+            .iter()
+            .filter(|element| element.id.as_ref() == "meta")
+            .find_map(|element| element.get("sequenceId"))
+            .and_then(|sequence_id| sequence_id.parse().ok())
+    }
+
+    /// Returns true if the message was parsed as RFC5424.
+    pub fn is_rfc5424(&self) -> bool {
+        matches!(self.protocol, Protocol::RFC5424(_))
+    }
+
+    /// Returns true if the message was parsed as RFC3164.
+    pub fn is_rfc3164(&self) -> bool {
+        self.protocol == Protocol::RFC3164
+    }
+
+    /// Returns the count from the summary rsyslog writes in place of repeated messages,
+    /// `last message repeated 3 times`. Returns `None` if the body isn't such a summary.
+    /// Since the summary has no tag, an RFC3164 message takes `last` to be the appname,
+    /// so this is also recognised.
+    pub fn repeated_count(&self) -> Option<u32> {
+        let msg = self.msg.as_ref();
+        let summary = match msg.strip_prefix("last ") {
+            Some(summary) => summary,
+            None if self.appname.as_ref().map(AsRef::as_ref) == Some("last")
+                && self.procid.is_none() =>
+            {
+                msg
+            }
+            None => return None,
+        };
+
+        summary
+            .strip_prefix("message repeated ")?
+            .strip_suffix(" times")
+            .filter(|count| !count.is_empty() && count.bytes().all(|c| c.is_ascii_digit()))?
+            .parse()
+            .ok()
+    }
+
+    /// Returns the numeric code of the facility.
+    pub fn facility_code(&self) -> Option<u8> {
+        self.facility.map(SyslogFacility::code)
+    }
+
+    /// Returns the numeric code of the severity.
+    pub fn severity_code(&self) -> Option<u8> {
+        self.severity.map(SyslogSeverity::code)
+    }
+
+    /// Returns the PRI value, composed from the facility and severity.
+    /// Returns `None` if either of them are missing.
+    pub fn pri(&self) -> Option<u8> {
+        Some(compose_pri(self.facility?, self.severity?) as u8)
+    }
+
+    /// Set the facility and severity of the message.
+    pub fn set_pri(&mut self, facility: SyslogFacility, severity: SyslogSeverity) {
+        self.facility = Some(facility);
+        self.severity = Some(severity);
+    }
+
+    /// Returns the message with the facility and severity replaced, eg. to rewrite
+    /// the priority of a message before relaying it.
+    pub fn with_pri(mut self, facility: SyslogFacility, severity: SyslogSeverity) -> Self {
+        self.set_pri(facility, severity);
+        self
+    }
+
+    /// Returns true if the message has the `kern` facility, which should only be used by the kernel.
+    pub fn is_kernel(&self) -> bool {
+        self.facility == Some(SyslogFacility::LOG_KERN)
+    }
+
+    /// Split the message body into fields separated by `|`, as used by some vendors.
+    /// A `|` escaped with a `\` does not separate fields, and the escape is left in the field.
+    pub fn parse_pipe_body(&self) -> Vec<&str> {
+        let msg = self.msg.as_ref();
+        let mut fields = Vec::new();
+        let mut start = 0;
+        let mut escaped = false;
+
+        for (idx, c) in msg.char_indices() {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '|' if !escaped => {
+                    fields.push(&msg[start..idx]);
+                    start = idx + 1;
+                }
+                _ => escaped = false,
+            }
+        }
+
+        fields.push(&msg[start..]);
+        fields
+    }
+
+    /// Parse the message body as a Linux audit record, as sent by auditd, eg.
+    /// `type=SYSCALL msg=audit(1609459200.123:456): arch=c000003e syscall=59`.
+    /// Returns `None` if the body is not an audit record.
+    pub fn parse_auditd(&self) -> Option<AuditRecord<'_>> {
+        auditd::parse(self.msg.as_ref())
+    }
+
+    /// Returns true if the message contains at least one structured data element.
+    pub fn is_structured(&self) -> bool {
+        !self.structured_data.is_empty()
+    }
+
+    /// Returns the first structured data element with the given id, eg. `exampleSDID@32473`.
+    /// The id must match exactly, including any enterprise number.
+    pub fn structured_element(&self, id: &str) -> Option<&structured_data::StructuredElement<S>> {
+        self.structured_data
+            .iter()
+            .find(|element| element.id.as_ref() == id)
+    }
+
+    /// Returns all the structured data elements with the given id, since an id can be repeated.
+    pub fn structured_elements<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> impl Iterator<Item = &'a structured_data::StructuredElement<S>> + 'a {
+        self.structured_data
+            .iter()
+            .filter(move |element| element.id.as_ref() == id)
+    }
+
+    /// Returns the typed contents of the RFC5424 `timeQuality` structured data element,
+    /// or `None` if the message doesn't have one.
+    pub fn time_quality(&self) -> Option<structured_data::TimeQuality> {
+        self.structured_element("timeQuality")
+            .map(structured_data::TimeQuality::from_element)
+    }
+
+    /// Returns the typed contents of the RFC5424 `origin` structured data element,
+    /// or `None` if the message doesn't have one.
+    pub fn origin(&self) -> Option<structured_data::Origin> {
+        self.structured_element("origin")
+            .map(structured_data::Origin::from_element)
+    }
+
+    /// Returns whether the sender knows its timezone, from the `tzKnown` param of the
+    /// `timeQuality` element. If it doesn't, the offset of the timestamp may not be reliable.
+    pub fn timezone_is_known(&self) -> Option<bool> {
+        self.time_quality()?.tz_known
+    }
+
+    /// Relays such as rsyslog can record the hostname as `ip/hostname`, eg. `192.0.2.1/host`.
+    /// Returns the hostname with any such ip removed. The `hostname` field keeps the ip.
+    pub fn relay_hostname(&self) -> Option<&str> {
+        self.hostname.as_ref().map(|hostname| {
+            split_relay_hostname(hostname.as_ref())
+                .map(|(_, hostname)| hostname)
+                .unwrap_or_else(|| hostname.as_ref())
+        })
+    }
+
+    /// Returns the ip from a hostname of the form `ip/hostname`, eg. `192.0.2.1/host`.
+    pub fn relay_ip(&self) -> Option<IpAddr> {
+        self.hostname
+            .as_ref()
+            .and_then(|hostname| split_relay_hostname(hostname.as_ref()))
+            .map(|(ip, _)| ip)
+    }
+
+    /// Returns every IPv4 and IPv6 address mentioned in the message body, in the order they appear.
+    /// An address may be followed by a port, eg. `192.0.2.1:514`.
+    pub fn extract_ips(&self) -> Vec<IpAddr> {
+        self.msg
+            .as_ref()
+            .split(|c: char| c.is_whitespace() || ",;()[]<>{}\"'=/+|".contains(c))
+            .filter_map(parse_ip_token)
+            .collect()
+    }
+
+    /// Returns an owned copy of the message in a canonical form, so that two messages that
+    /// mean the same thing compare as equal. The params of each structured data element are
+    /// sorted, the elements are sorted by id, and any optional field containing the NILVALUE `-`
+    /// is set to `None`.
+    pub fn canonical(&self) -> Message<String> {
+        fn nil_to_none<S: AsRef<str>>(field: &Option<S>) -> Option<String> {
+            field
+                .as_ref()
+                .map(|s| s.as_ref())
+                .filter(|s| *s != "-")
+                .map(|s| s.to_string())
+        }
+
+        let mut structured_data = self.structured_data_owned();
+        for element in structured_data.iter_mut() {
+            element.params.sort();
+        }
+        structured_data.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.params.cmp(&b.params)));
+
+        let mut unparsed_structured_data = self
+            .unparsed_structured_data
+            .iter()
+            .map(|s| s.as_ref().to_string())
+            .collect::<Vec<_>>();
+        unparsed_structured_data.sort();
+
+        Message {
+            protocol: self.protocol.clone(),
+            facility: self.facility,
+            severity: self.severity,
+            timestamp: self.timestamp,
+            raw_timestamp: self.raw_timestamp.as_ref().map(|s| s.as_ref().to_string()),
+            hostname: nil_to_none(&self.hostname),
+            appname: nil_to_none(&self.appname),
+            procid: match &self.procid {
+                Some(ProcId::Name(name)) if name.as_ref() == "-" => None,
+                Some(ProcId::Name(name)) => Some(ProcId::Name(name.as_ref().to_string())),
+                Some(ProcId::PID(pid)) => Some(ProcId::PID(*pid)),
+                None => None,
+            },
+            msgid: nil_to_none(&self.msgid),
+            structured_data,
+            unparsed_structured_data,
+            had_bom: self.had_bom,
+            msg: self.msg.as_ref().to_string(),
+        }
+    }
+
+    /// Returns a fingerprint of every field except the timestamp, for deduplicating repeated
+    /// messages. Messages that are equal apart from their timestamps share a fingerprint.
+    /// As with `eq`, the order of the params within a structured data element is ignored.
+    /// The fingerprint is a 64 bit FNV-1a hash of the fields, so it is stable across
+    /// processes and versions of Rust.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+
+        hasher.write_opt_code(self.facility.map(SyslogFacility::code));
+        hasher.write_opt_code(self.severity.map(SyslogSeverity::code));
+        hasher.write_opt_str(self.hostname.as_ref().map(AsRef::as_ref));
+        hasher.write_opt_str(self.appname.as_ref().map(AsRef::as_ref));
+        match &self.procid {
+            Some(ProcId::PID(pid)) => {
+                hasher.write(&[1]);
+                hasher.write(&pid.to_le_bytes());
+            }
+            Some(ProcId::Name(name)) => {
+                hasher.write(&[2]);
+                hasher.write_str(name.as_ref());
+            }
+            None => hasher.write(&[0]),
+        }
+        hasher.write_opt_str(self.msgid.as_ref().map(AsRef::as_ref));
+
+        hasher.write_len(self.structured_data.len());
+        for element in &self.structured_data {
+            let mut params = element
+                .params
+                .iter()
+                .map(|(name, value)| (name.as_ref(), value.as_ref()))
+                .collect::<Vec<_>>();
+            params.sort_unstable();
+
+            hasher.write_str(element.id.as_ref());
+            hasher.write_len(params.len());
+            for (name, value) in params {
+                hasher.write_str(name);
+                hasher.write_str(value);
+            }
+        }
+
+        hasher.write_len(self.unparsed_structured_data.len());
+        for unparsed in &self.unparsed_structured_data {
+            hasher.write_str(unparsed.as_ref());
+        }
+
+        hasher.write_str(self.msg.as_ref());
+        hasher.0
+    }
+
+    /// Compare two messages, treating any optional field that contains the NILVALUE `-`
+    /// as being equivalent to `None`.
+    pub fn eq_nilaware(&self, other: &Self) -> bool {
+        fn nil_aware<S: AsRef<str>>(field: &Option<S>) -> Option<&str> {
+            field.as_ref().map(|s| s.as_ref()).filter(|s| *s != "-")
+        }
+
+        fn nil_aware_procid<S: AsRef<str> + Ord + Clone>(
+            procid: &Option<ProcId<S>>,
+        ) -> Option<&ProcId<S>> {
+            procid.as_ref().filter(|procid| match procid {
+                ProcId::Name(name) => name.as_ref() != "-",
+                ProcId::PID(_) => true,
+            })
+        }
+
+        self.facility == other.facility
+            && self.severity == other.severity
+            && self.timestamp == other.timestamp
+            && nil_aware(&self.hostname) == nil_aware(&other.hostname)
+            && nil_aware(&self.appname) == nil_aware(&other.appname)
+            && nil_aware_procid(&self.procid) == nil_aware_procid(&other.procid)
+            && nil_aware(&self.msgid) == nil_aware(&other.msgid)
+            && self.structured_data == other.structured_data
+            && self.unparsed_structured_data == other.unparsed_structured_data
             && self.msg == other.msg
     }
 }
 
+impl<S: AsRef<str> + Ord + Clone> Message<S> {
+    /// Convert all the strings in the message to another string type.
+    pub(crate) fn map_str<T, F>(self, f: F) -> Message<T>
+    where
+        T: AsRef<str> + Ord + Clone,
+        F: Fn(S) -> T,
+    {
+        Message {
+            protocol: self.protocol,
+            facility: self.facility,
+            severity: self.severity,
+            timestamp: self.timestamp,
+            raw_timestamp: self.raw_timestamp.map(&f),
+            hostname: self.hostname.map(&f),
+            appname: self.appname.map(&f),
+            procid: self.procid.map(|procid| procid.map_str(&f)),
+            msgid: self.msgid.map(&f),
+            structured_data: self
+                .structured_data
+                .into_iter()
+                .map(|element| element.map_str(&f))
+                .collect(),
+            unparsed_structured_data: self.unparsed_structured_data.into_iter().map(&f).collect(),
+            had_bom: self.had_bom,
+            msg: f(self.msg),
+        }
+    }
+}
+
+/// Builds a `Message` a field at a time, eg. to generate messages to send.
+#[derive(Clone, Debug)]
+pub struct MessageBuilder<S: AsRef<str> + Ord + Clone> {
+    message: Message<S>,
+}
+
+impl<S: AsRef<str> + Ord + Clone> MessageBuilder<S> {
+    /// Set the protocol.
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.message.protocol = protocol;
+        self
+    }
+
+    /// Set the facility.
+    pub fn with_facility(mut self, facility: SyslogFacility) -> Self {
+        self.message.facility = Some(facility);
+        self
+    }
+
+    /// Set the severity.
+    pub fn with_severity(mut self, severity: SyslogSeverity) -> Self {
+        self.message.severity = Some(severity);
+        self
+    }
+
+    /// Set the timestamp.
+    pub fn with_timestamp(mut self, timestamp: DateTime<FixedOffset>) -> Self {
+        self.message.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Set the hostname.
+    pub fn with_hostname(mut self, hostname: S) -> Self {
+        self.message.hostname = Some(hostname);
+        self
+    }
+
+    /// Set the appname.
+    pub fn with_appname(mut self, appname: S) -> Self {
+        self.message.appname = Some(appname);
+        self
+    }
+
+    /// Set the procid.
+    pub fn with_procid(mut self, procid: ProcId<S>) -> Self {
+        self.message.procid = Some(procid);
+        self
+    }
+
+    /// Set the msgid.
+    pub fn with_msgid(mut self, msgid: S) -> Self {
+        self.message.msgid = Some(msgid);
+        self
+    }
+
+    /// Add a structured data element, see `StructuredElement::builder`.
+    pub fn with_structured_element(
+        mut self,
+        element: structured_data::StructuredElement<S>,
+    ) -> Self {
+        self.message.structured_data.push(element);
+        self
+    }
+
+    /// Replace all the structured data elements.
+    pub fn with_structured_data(
+        mut self,
+        structured_data: Vec<structured_data::StructuredElement<S>>,
+    ) -> Self {
+        self.message.structured_data = structured_data;
+        self
+    }
+
+    /// Replace the message body.
+    pub fn with_msg(mut self, msg: S) -> Self {
+        self.message.msg = msg;
+        self
+    }
+
+    /// Returns the built message.
+    pub fn build(self) -> Message<S> {
+        self.message
+    }
+}
+
+/// The fields of a message, returned by `Message::into_parts`.
+/// A message can be put back together from its parts with `Message::from`.
+#[derive(Clone, Debug)]
+pub struct MessageParts<S: AsRef<str> + Ord + PartialEq + Clone> {
+    pub protocol: Protocol,
+    pub facility: Option<SyslogFacility>,
+    pub severity: Option<SyslogSeverity>,
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    pub raw_timestamp: Option<S>,
+    /// The hostname as it was written. A relay can record it as `ip/hostname`, eg.
+    /// `192.0.2.1/host`, which is kept whole here since the field borrows from the input
+    /// and so can't also hold the ip. Use `relay_hostname` and `relay_ip` to split it.
+    pub hostname: Option<S>,
+    pub appname: Option<S>,
+    pub procid: Option<ProcId<S>>,
+    pub msgid: Option<S>,
+    pub structured_data: Vec<structured_data::StructuredElement<S>>,
+    pub unparsed_structured_data: Vec<S>,
+    pub had_bom: bool,
+    pub msg: S,
+}
+
+impl<S: AsRef<str> + Ord + PartialEq + Clone> From<MessageParts<S>> for Message<S> {
+    fn from(parts: MessageParts<S>) -> Self {
+        Message {
+            protocol: parts.protocol,
+            facility: parts.facility,
+            severity: parts.severity,
+            timestamp: parts.timestamp,
+            raw_timestamp: parts.raw_timestamp,
+            hostname: parts.hostname,
+            appname: parts.appname,
+            procid: parts.procid,
+            msgid: parts.msgid,
+            structured_data: parts.structured_data,
+            unparsed_structured_data: parts.unparsed_structured_data,
+            had_bom: parts.had_bom,
+            msg: parts.msg,
+        }
+    }
+}
+
+/// Details of how a message was detected and parsed, returned by `parse_message_with_metadata`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseMetadata {
+    /// The variant the message was parsed as. Never `Variant::Either`.
+    /// When `fallback` is set this is `Variant::RFC3164`, matching the message's protocol.
+    pub variant: Variant,
+    /// Whether the message started with a priority, eg. `<34>`.
+    pub pri_present: bool,
+    /// Whether the message couldn't be parsed, so the whole input was taken as the message.
+    pub fallback: bool,
+    /// The framing the message was sent with.
+    pub framing: Framing,
+}
+
+/// Statistics about how a message was parsed, returned by `parse_message_with_stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// The number of structured data elements that could not be parsed and were dropped.
+    /// Their raw text is kept in `Message::unparsed_structured_data`.
+    pub dropped_structured_data: usize,
+}
+
+/// A message parsed from raw bytes by `parse_message_bytes`.
+/// Derefs to the parsed `Message`, but also keeps hold of the raw bytes of the msg
+/// so they can be decoded with a different encoding.
+#[derive(Clone, Debug)]
+pub struct BytesMessage<'a> {
+    pub(crate) message: Message<Cow<'a, str>>,
+    pub(crate) raw_msg: &'a [u8],
+}
+
+impl<'a> BytesMessage<'a> {
+    /// The raw bytes of the msg, before they were decoded.
+    pub fn raw_msg(&self) -> &'a [u8] {
+        self.raw_msg
+    }
+
+    /// Decode the raw bytes of the msg as Latin-1 (ISO-8859-1), for sources that don't send UTF-8.
+    /// Every byte maps directly to the unicode code point of the same value, so this can't fail.
+    pub fn msg_lossy_latin1(&self) -> String {
+        self.raw_msg.iter().map(|&b| char::from(b)).collect()
+    }
+
+    /// Returns the parsed message, dropping the raw bytes.
+    pub fn into_message(self) -> Message<Cow<'a, str>> {
+        self.message
+    }
+}
+
+impl<'a> Deref for BytesMessage<'a> {
+    type Target = Message<Cow<'a, str>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.message
+    }
+}
+
 impl From<Message<&str>> for Message<String> {
     fn from(message: Message<&str>) -> Self {
         Message {
             facility: message.facility,
             severity: message.severity,
             timestamp: message.timestamp,
+            raw_timestamp: message.raw_timestamp.map(|s| s.to_string()),
             hostname: message.hostname.map(|s| s.to_string()),
             appname: message.appname.map(|s| s.to_string()),
             procid: message.procid.map(|s| s.into()),
@@ -118,7 +855,435 @@ impl From<Message<&str>> for Message<String> {
                 .iter()
                 .map(|e| e.clone().into())
                 .collect(),
+            unparsed_structured_data: message
+                .unparsed_structured_data
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            had_bom: message.had_bom,
             msg: message.msg.to_string(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structured_data::StructuredElement;
+
+    fn message(hostname: Option<&str>) -> Message<&str> {
+        Message {
+            protocol: Protocol::RFC5424(1),
+            facility: None,
+            severity: None,
+            timestamp: None,
+            raw_timestamp: None,
+            hostname,
+            appname: None,
+            procid: None,
+            msgid: None,
+            structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
+            msg: "message",
+        }
+    }
+
+    #[test]
+    fn fingerprint() {
+        let mut a = message(Some("host"));
+        a.timestamp = Some(Utc.fix().with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
+        a.structured_data = vec![StructuredElement {
+            id: "meta",
+            params: vec![("sequenceId", "1"), ("language", "EN")],
+        }];
+
+        // The fingerprint is stable, so it can be compared across processes.
+        assert_eq!(a.fingerprint(), 0x6916_2cc0_53c4_5aef);
+
+        let mut b = a.clone();
+        b.timestamp = Some(Utc.fix().with_ymd_and_hms(2021, 6, 8, 11, 54, 8).unwrap());
+        b.structured_data[0].params.reverse();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        b.timestamp = None;
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        b.msg = "another message";
+        assert_ne!(a.fingerprint(), b.fingerprint());
+
+        let mut c = a.clone();
+        c.procid = Some(ProcId::Name("1"));
+        let mut d = a.clone();
+        d.procid = Some(ProcId::PID(1));
+        assert_ne!(c.fingerprint(), d.fingerprint());
+
+        let mut e = message(Some("host"));
+        e.facility = Some(SyslogFacility::LOG_AUTH);
+        e.severity = Some(SyslogSeverity::SEV_CRIT);
+        e.appname = Some("app");
+        e.procid = Some(ProcId::PID(1234));
+        e.msgid = Some("ID47");
+        e.unparsed_structured_data = vec!["[bad"];
+        assert_eq!(e.fingerprint(), 0x25f6_3842_be24_71bc);
+    }
+
+    #[test]
+    fn into_parts() {
+        let mut message = message(Some("host"));
+        message.structured_data = vec![StructuredElement {
+            id: "meta",
+            params: vec![("sequenceId", "1")],
+        }];
+
+        let parts = message.clone().into_parts();
+        assert_eq!(parts.hostname, Some("host"));
+        assert_eq!(parts.structured_data, message.structured_data);
+        assert_eq!(parts.msg, "message");
+
+        assert_eq!(Message::from(parts), message);
+    }
+
+    #[test]
+    fn with_timezone() {
+        let timestamp = FixedOffset::west_opt(5 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2020, 1, 1, 19, 0, 0)
+            .unwrap();
+        let mut original = message(None);
+        original.timestamp = Some(timestamp);
+
+        let converted = original.clone().with_timezone(Utc);
+        assert_eq!(
+            converted.timestamp.unwrap().to_rfc3339(),
+            "2020-01-02T00:00:00+00:00"
+        );
+        // The instant is the same, so the messages are still equal.
+        assert_eq!(converted, original);
+
+        let converted = original.with_timezone(FixedOffset::east_opt(3600).unwrap());
+        assert_eq!(
+            converted.timestamp.unwrap().to_rfc3339(),
+            "2020-01-02T01:00:00+01:00"
+        );
+
+        assert_eq!(message(None).with_timezone(Utc).timestamp, None);
+    }
+
+    #[test]
+    fn protocol_predicates() {
+        let mut message = message(None);
+        assert!(message.is_rfc5424());
+        assert!(!message.is_rfc3164());
+        assert_eq!(message.protocol.version(), Some(1));
+
+        message.protocol = Protocol::RFC3164;
+        assert!(!message.is_rfc5424());
+        assert!(message.is_rfc3164());
+        assert_eq!(message.protocol.version(), None);
+    }
+
+    #[test]
+    fn repeated_count() {
+        let mut message = message(None);
+        message.msg = "last message repeated 3 times";
+        assert_eq!(message.repeated_count(), Some(3));
+
+        for msg in [
+            "message",
+            "last message repeated times",
+            "last message repeated +3 times",
+            "last message repeated 3 times: [message]",
+            "the last message repeated 3 times",
+        ]
+        .iter()
+        {
+            message.msg = msg;
+            assert_eq!(message.repeated_count(), None, "{}", msg);
+        }
+
+        message.appname = Some("last");
+        message.msg = "message repeated 12 times";
+        assert_eq!(message.repeated_count(), Some(12));
+    }
+
+    #[test]
+    fn params_map() {
+        let mut msg = message(None);
+        msg.structured_data = vec![
+            StructuredElement {
+                id: "meta",
+                params: vec![("sequenceId", "1"), ("text", r#"a \"quote\""#)],
+            },
+            StructuredElement {
+                id: "origin",
+                params: vec![("ip", "192.0.2.1")],
+            },
+            StructuredElement {
+                id: "origin",
+                params: vec![("ip", "192.0.2.2")],
+            },
+        ];
+
+        let map = msg.params_map();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[&("meta", "sequenceId")], "1");
+        assert_eq!(map[&("meta", "text")], r#"a "quote""#);
+        assert_eq!(map[&("origin", "ip")], "192.0.2.2");
+    }
+
+    #[test]
+    fn structured_element() {
+        let mut msg = message(None);
+        msg.structured_data = vec![
+            StructuredElement {
+                id: "origin@123",
+                params: vec![("ip", "192.0.2.1")],
+            },
+            StructuredElement {
+                id: "meta",
+                params: vec![("sequenceId", "1")],
+            },
+            StructuredElement {
+                id: "origin@123",
+                params: vec![("ip", "192.0.2.2")],
+            },
+        ];
+
+        assert_eq!(
+            msg.structured_element("origin@123")
+                .map(|element| element.params.clone()),
+            Some(vec![("ip", "192.0.2.1")])
+        );
+        assert_eq!(msg.structured_element("origin"), None);
+        assert_eq!(msg.structured_elements("origin@123").count(), 2);
+        assert_eq!(msg.structured_elements("missing").count(), 0);
+    }
+
+    #[test]
+    fn time_quality() {
+        let mut msg = message(None);
+        assert_eq!(msg.time_quality(), None);
+
+        msg.structured_data = vec![StructuredElement {
+            id: "timeQuality",
+            params: vec![
+                ("tzKnown", "1"),
+                ("isSynced", "0"),
+                ("syncAccuracy", "60000000"),
+            ],
+        }];
+        assert_eq!(
+            msg.time_quality(),
+            Some(structured_data::TimeQuality {
+                tz_known: Some(true),
+                is_synced: Some(false),
+                sync_accuracy: Some(60000000),
+            })
+        );
+
+        msg.structured_data = vec![StructuredElement {
+            id: "timeQuality",
+            params: vec![("isSynced", "yes")],
+        }];
+        assert_eq!(
+            msg.time_quality(),
+            Some(structured_data::TimeQuality::default())
+        );
+    }
+
+    #[test]
+    fn canonical() {
+        let mut a = message(Some("-"));
+        a.msgid = Some("-");
+        a.procid = Some(ProcId::Name("-"));
+        a.structured_data = vec![
+            StructuredElement {
+                id: "origin",
+                params: vec![("software", "test"), ("ip", "192.0.2.1")],
+            },
+            StructuredElement {
+                id: "meta",
+                params: vec![("sequenceId", "1")],
+            },
+        ];
+
+        let mut b = message(None);
+        b.structured_data = vec![
+            StructuredElement {
+                id: "meta",
+                params: vec![("sequenceId", "1")],
+            },
+            StructuredElement {
+                id: "origin",
+                params: vec![("ip", "192.0.2.1"), ("software", "test")],
+            },
+        ];
+
+        assert_ne!(a, b);
+        assert_eq!(a.canonical(), b.canonical());
+        assert_eq!(a.canonical().hostname, None);
+        assert_eq!(a.canonical().structured_data[0].id, "meta");
+    }
+
+    #[test]
+    fn pri() {
+        let mut msg = message(None);
+        assert_eq!(msg.pri(), None);
+
+        msg.facility = Some(SyslogFacility::LOG_AUTH);
+        msg.severity = Some(SyslogSeverity::SEV_CRIT);
+        assert_eq!(msg.pri(), Some(34));
+        assert_eq!(msg.facility_code(), Some(4));
+        assert_eq!(msg.severity_code(), Some(2));
+    }
+
+    #[test]
+    fn write_with_precision() {
+        let mut msg = message(Some("host"));
+        msg.timestamp = Some(
+            FixedOffset::east_opt(0)
+                .unwrap()
+                .with_ymd_and_hms(2003, 10, 11, 22, 14, 15)
+                .unwrap()
+                .with_nanosecond(123_456_789)
+                .unwrap(),
+        );
+
+        let mut millis = String::new();
+        msg.write_with_precision(&mut millis, SecondsFormat::Millis)
+            .unwrap();
+        assert_eq!(
+            millis,
+            "<47>1 2003-10-11T22:14:15.123+00:00 host - - - - message"
+        );
+
+        assert_eq!(
+            msg.to_string(),
+            "<47>1 2003-10-11T22:14:15.123456789+00:00 host - - - - message"
+        );
+    }
+
+    #[test]
+    fn display_without_timestamp() {
+        let msg = message(Some("host"));
+        assert_eq!(msg.to_string(), "<47>1 - host - - - - message");
+
+        let msg = Message {
+            protocol: Protocol::RFC3164,
+            appname: Some("app"),
+            ..message(Some("host"))
+        };
+        let displayed = msg.to_string();
+        assert_eq!(displayed, "<47>host app: message");
+
+        // Parsing, displaying and parsing again gives the same message.
+        let options = crate::ParseOptions {
+            bracketed_timestamp: true,
+            ..Default::default()
+        };
+        let parsed =
+            crate::parse_message_with_options("<47>host app: message", Variant::RFC3164, options);
+        assert_eq!(parsed.timestamp, None);
+        assert_eq!(parsed.hostname, Some("host"));
+        assert_eq!(parsed.appname, Some("app"));
+        let displayed = parsed.to_string();
+        assert_eq!(displayed, "<47>host app: message");
+        assert_eq!(
+            crate::parse_message_with_options(&displayed, Variant::RFC3164, options),
+            parsed
+        );
+    }
+
+    #[test]
+    fn with_pri() {
+        let msg =
+            message(Some("host")).with_pri(SyslogFacility::LOG_LOCAL7, SyslogSeverity::SEV_INFO);
+        assert_eq!(msg.pri(), Some(190));
+        assert!(msg.to_string().starts_with("<190>1 "));
+
+        let mut msg = msg;
+        msg.set_pri(SyslogFacility::LOG_AUTH, SyslogSeverity::SEV_CRIT);
+        assert!(msg.to_string().starts_with("<34>1 "));
+    }
+
+    #[test]
+    fn builder() {
+        let timestamp = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2003, 10, 11, 22, 14, 15)
+            .unwrap();
+        let msg = Message::builder("message")
+            .with_facility(SyslogFacility::LOG_AUTH)
+            .with_severity(SyslogSeverity::SEV_CRIT)
+            .with_timestamp(timestamp)
+            .with_hostname("host")
+            .with_appname("su")
+            .with_procid(ProcId::PID(42))
+            .with_msgid("ID47")
+            .with_structured_element(
+                StructuredElement::builder("meta")
+                    .with_param("sequenceId", "1")
+                    .build(),
+            )
+            .build();
+
+        let formatted = msg.to_string();
+        assert_eq!(
+            formatted,
+            r#"<34>1 2003-10-11T22:14:15+00:00 host su 42 ID47 [meta sequenceId="1"] message"#
+        );
+        assert_eq!(
+            crate::parse_message(&formatted, crate::Variant::RFC5424),
+            msg
+        );
+
+        let msg = Message::builder("message")
+            .with_protocol(Protocol::RFC3164)
+            .with_msg("replaced")
+            .build();
+        assert_eq!(msg.facility, None);
+        assert_eq!(msg.timestamp, None);
+        assert_eq!(msg.hostname, None);
+        assert_eq!(msg.msg, "replaced");
+    }
+
+    #[test]
+    fn is_kernel() {
+        let kernel = Message {
+            facility: Some(SyslogFacility::LOG_KERN),
+            ..message(None)
+        };
+        let user = Message {
+            facility: Some(SyslogFacility::LOG_USER),
+            ..message(None)
+        };
+
+        assert!(kernel.is_kernel());
+        assert!(!user.is_kernel());
+        assert!(!message(None).is_kernel());
+    }
+
+    #[test]
+    fn parse_pipe_body() {
+        let message = Message {
+            msg: r#"vendor|product\|suite|1.0"#,
+            ..message(None)
+        };
+
+        assert_eq!(
+            message.parse_pipe_body(),
+            vec!["vendor", r#"product\|suite"#, "1.0"]
+        );
+        assert_eq!(message.msg, r#"vendor|product\|suite|1.0"#);
+    }
+
+    #[test]
+    fn eq_nilaware_hostname() {
+        assert!(message(Some("-")).eq_nilaware(&message(None)));
+        assert!(message(None).eq_nilaware(&message(Some("-"))));
+        assert!(message(Some("host")).eq_nilaware(&message(Some("host"))));
+        assert!(!message(Some("host")).eq_nilaware(&message(None)));
+        assert!(message(Some("-")) != message(None));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{message::Message, pri::FacilityMap, timestamp::IncompleteDate, Variant};
+use chrono::FixedOffset;
+
+/// Determines what marks the end of the header fields in an RFC3164 message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeaderBoundary {
+    /// The header ends at either a `:` or at a run of two spaces or a tab.
+    /// This is the historic behaviour of the parser.
+    #[default]
+    Either,
+    /// The header only ends at a `:`. Runs of spaces between the header fields
+    /// are skipped over.
+    Colon,
+    /// The header only ends at a run of two or more spaces, or a tab. A `:` directly following
+    /// the last header field is removed, but colons do not otherwise end the header.
+    TwoSpaces,
+}
+
+/// Options that tweak how loosely a message is parsed.
+///
+/// The default options match the behaviour of `parse_message`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+    /// The variant of message we are expecting to receive.
+    /// Only used by `parse_with_options`, the other functions take the variant as an argument.
+    pub variant: Variant,
+
+    /// A default timezone to use if the parsed timestamp does not specify one. If `None` the
+    /// local timezone is used. Only used by `parse_with_options`.
+    pub timezone: Option<FixedOffset>,
+
+    /// Called if the parsed message contains a date with no year, to return the year to use.
+    /// If `None` the current year is used. Only used by `parse_with_options`.
+    pub get_year: Option<fn(IncompleteDate) -> i32>,
+
+    /// The maximum length of input to parse. Longer input fails to parse rather than being
+    /// parsed, guarding against giant inputs. Only used by `parse_with_options`.
+    pub max_len: Option<usize>,
+
+    /// How the end of the header is detected in RFC3164 messages.
+    pub header_boundary: HeaderBoundary,
+
+    /// Allow the RFC3164 tag to contain spaces. When set, if a `:` follows the hostname
+    /// the tag is taken to be everything up to that `:`, eg. `my daemon: msg`.
+    pub spaces_in_tag: bool,
+
+    /// Lowercase the hostname. Since this needs to allocate a new string, it is only
+    /// applied when parsing into an owned `Message<String>` with `parse_message_with_options_owned`.
+    pub lowercase_hostname: bool,
+
+    /// Some non-compliant senders place the structured data at the end of an RFC5424 message.
+    /// When set, and no structured data is found in its proper place, any structured data
+    /// at the very end of the message is moved out of the message.
+    pub trailing_structured_data: bool,
+
+    /// Accept a priority written in hexadecimal, eg. `<0x22>`, as sent by some embedded devices.
+    pub hex_pri: bool,
+
+    /// Only the kernel should send messages with the `kern` facility. When set, any message
+    /// with the `kern` facility is given the `user` facility instead. Useful for untrusted sources.
+    pub remap_kernel_facility: bool,
+
+    /// The maximum number of structured data elements to parse. Any further elements
+    /// are left as part of the message. `None` means there is no limit, and `Some(0)`
+    /// leaves all the structured data in the message.
+    pub max_sd_elements: Option<usize>,
+
+    /// Some applications start the message with a timestamp in square brackets,
+    /// eg. `[2021-06-08 11:54:08] message`, and send it with no syslog timestamp.
+    /// When set, the syslog timestamp of an RFC3164 message is optional, and if it is
+    /// missing a leading bracketed timestamp is lifted out of the message.
+    pub bracketed_timestamp: bool,
+
+    /// RFC5424 allows the message to be prefixed with a UTF-8 byte order mark, which is
+    /// removed from the message by default, in RFC3164 messages too. When set, the BOM is
+    /// left in the message.
+    pub keep_bom: bool,
+
+    /// RFC5424 requires the message to start with a priority, eg. `<34>`, but by default
+    /// messages without one are accepted. When set, an RFC5424 message without a priority
+    /// fails to parse.
+    pub require_pri: bool,
+
+    /// Replace the hostnames that refer to the local machine, `localhost.localdomain`,
+    /// `127.0.0.1` and `::1`, with `localhost` so messages from them can be deduplicated.
+    pub normalize_localhost: bool,
+
+    /// Some RFC5424 senders put the procid in the appname, eg. `app:123`. When set, and there
+    /// is no procid, an appname of the form `name:digits` is split into the appname and procid.
+    /// RFC3164 messages are not affected, since their tags can legitimately contain colons.
+    pub split_appname_procid: bool,
+
+    /// By default all the whitespace between the header and the message body is removed.
+    /// When set, only the single space or tab separating them is removed, so a message body
+    /// that starts with indentation keeps it. In RFC3164 messages this applies to the
+    /// whitespace following the `:` that ends the header.
+    pub keep_msg_whitespace: bool,
+
+    /// Some forwarders prepend a sequence number to each message, eg. `12345: <34>...`.
+    /// When set, a sequence number followed by a `:` before the priority is skipped over.
+    pub skip_sequence_prefix: bool,
+
+    /// By default whitespace is trimmed from the start and end of the input before parsing.
+    /// When set the input is parsed as is, so any trailing whitespace is kept in the message.
+    pub no_trim: bool,
+
+    /// RFC5424 requires structured data param values to be surrounded by `"`. When set, values
+    /// surrounded by `'` are also accepted, eg. `[x y='hello world']`, with `\'` escaping a `'`.
+    /// Param values are borrowed from the input and unescaped when read, as RFC5424 describes
+    /// for double quoted values, so the `\` of a `\'` is kept in the value.
+    pub single_quoted_sd_values: bool,
+
+    /// By default an RFC3164 message may have an RFC3339 timestamp, eg. `2020-10-11T22:14:15Z`.
+    /// When set only the RFC3164 timestamp formats, such as `Oct 11 22:14:15`, are accepted.
+    pub strict_rfc3164_timestamp: bool,
+
+    /// Some non-compliant devices write the month of an RFC3164 timestamp as a two digit
+    /// number, eg. `01 05 15:33:03`. When set, a month of `01` to `12` is accepted.
+    pub numeric_month: bool,
+
+    /// By default any structured data id is accepted. When set, an element whose id isn't
+    /// one of the IANA registered ids, such as `timeQuality`, or of the form `name@number`,
+    /// or that has a param name containing invalid characters, fails to parse. In RFC5424
+    /// messages the element is then kept in `Message::unparsed_structured_data`.
+    pub strict_sd_ids: bool,
+
+    /// Some resolvers write fully qualified hostnames with a trailing dot, eg.
+    /// `mymachine.example.com.`. The dot is kept by default. When set, it is removed.
+    pub trim_hostname_dot: bool,
+
+    /// Some legacy relays strip the angle brackets from the priority, eg. `34 Oct 11 22:14:15 host: msg`.
+    /// When set, a number from 0 to 191 at the start of the message, followed by whitespace and
+    /// then something other than a digit, is taken as the priority. Since a message could
+    /// genuinely start with a number, only set this for sources known to send bare priorities.
+    pub bare_pri: bool,
+
+    /// Some platforms number the facilities differently. When set, the facility of the
+    /// priority is looked up in this map rather than using the standard numbering.
+    /// This only affects parsing, displaying a message always uses the standard numbering.
+    pub facility_map: Option<FacilityMap>,
+}
+
+/// The hostnames that are replaced with `localhost` by the `normalize_localhost` option.
+const LOCALHOST_ALIASES: &[&str] = &["localhost", "localhost.localdomain", "127.0.0.1", "::1"];
+
+impl ParseOptions {
+    /// Apply any options that rewrite the fields of a borrowed message.
+    pub(crate) fn normalize_borrowed<'a>(&self, mut message: Message<&'a str>) -> Message<&'a str> {
+        if self.trim_hostname_dot {
+            message.hostname = message
+                .hostname
+                .map(|hostname| hostname.strip_suffix('.').unwrap_or(hostname));
+        }
+
+        if self.normalize_localhost {
+            message.hostname = message.hostname.map(|hostname| {
+                if LOCALHOST_ALIASES
+                    .iter()
+                    .any(|alias| hostname.eq_ignore_ascii_case(alias))
+                {
+                    "localhost"
+                } else {
+                    hostname
+                }
+            });
+        }
+
+        message
+    }
+
+    /// Apply any options that rewrite the fields of an owned message.
+    pub(crate) fn normalize(&self, mut message: Message<String>) -> Message<String> {
+        if self.lowercase_hostname {
+            message.hostname = message.hostname.map(|hostname| hostname.to_lowercase());
+        }
+
+        message
+    }
+}
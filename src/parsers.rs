@@ -1,13 +1,18 @@
 //! Parsers shared by both protocols.
+//!
+//! The public parsers in this module can be used to compose your own nom parsers.
 use nom::{
-    bytes::complete::take_while1,
-    character::complete::digit1,
-    combinator::map_res,
+    branch::alt,
+    bytes::complete::{take_until, take_while1, take_while_m_n},
+    character::complete::{digit1, space0},
+    combinator::{map, map_res, verify},
     error::{make_error, ErrorKind},
     Err, IResult,
 };
 use std::str::FromStr;
 
+pub use crate::pri::parse_pri;
+
 pub(crate) fn digits<T>(input: &str) -> IResult<&str, T>
 where
     T: FromStr,
@@ -47,6 +52,20 @@ pub(crate) fn tagname(input: &str) -> IResult<&str, Option<&str>> {
     optional(input, false)
 }
 
+/// Parse the tagname, allowing it to contain spaces provided it is terminated by a ':'.
+/// If there is no ':' this falls back to parsing the tagname up to whitespace.
+pub(crate) fn tagname_with_spaces(input: &str) -> IResult<&str, Option<&str>> {
+    alt((
+        map(
+            verify(take_until(":"), |value: &str| {
+                !value.starts_with(char::is_whitespace) && !value.trim().is_empty()
+            }),
+            |value: &str| Some(value.trim_end()),
+        ),
+        tagname,
+    ))(input)
+}
+
 /// Parse the app name
 pub(crate) fn appname(input: &str) -> IResult<&str, Option<&str>> {
     optional(input, true)
@@ -62,6 +81,17 @@ pub(crate) fn msgid(input: &str) -> IResult<&str, Option<&str>> {
     optional(input, true)
 }
 
+/// Parse the whitespace separating the header from the message body.
+/// If `keep_whitespace` is set only a single space or tab is taken, so any further
+/// whitespace is left at the start of the message.
+pub(crate) fn msg_separator(keep_whitespace: bool) -> fn(&str) -> IResult<&str, &str> {
+    if keep_whitespace {
+        |input| take_while_m_n(0, 1, |c: char| c == ' ' || c == '\t')(input)
+    } else {
+        |input| space0(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,6 +120,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hostname_with_underscore() {
+        assert_eq!(hostname("my_host "), Ok((" ", Some("my_host"))));
+
+        let message = crate::parse_message(
+            "<34>Oct 11 22:14:15 my_host app: msg",
+            crate::Variant::RFC3164,
+        );
+        assert_eq!(message.hostname, Some("my_host"));
+        assert_eq!(message.appname, Some("app"));
+    }
+
+    #[test]
+    fn tagname_spaces_until_colon() {
+        assert_eq!(
+            tagname_with_spaces("my daemon: msg"),
+            Ok((": msg", Some("my daemon")))
+        );
+        assert_eq!(tagname_with_spaces("app msg"), Ok((" msg", Some("app"))));
+    }
+
     #[test]
     fn trailing_colon() {
         assert_eq!(hostname("zork: "), Ok((": ", Some("zork"))))
@@ -1,5 +1,11 @@
-use crate::parsers::digits;
-use nom::{bytes::complete::tag, combinator::map, combinator::opt, sequence::delimited, IResult};
+use crate::{options::ParseOptions, parsers::digits};
+use nom::{
+    bytes::complete::{tag, tag_no_case},
+    character::complete::{digit1, hex_digit1, space0, space1},
+    combinator::{map, map_res, not, opt, peek, verify},
+    sequence::{delimited, preceded, terminated, tuple},
+    IResult,
+};
 
 // Taken from https://github.com/Roguelazer/rust-syslog-rfc5424/blob/af76363081314f91433e014c76fd834acef756d5/src/facility.rs
 // Many thanks.
@@ -37,8 +43,38 @@ pub enum SyslogFacility {
 }
 
 impl SyslogFacility {
+    /// All the facilities, in order of their numeric code.
+    pub fn all() -> &'static [SyslogFacility] {
+        &[
+            SyslogFacility::LOG_KERN,
+            SyslogFacility::LOG_USER,
+            SyslogFacility::LOG_MAIL,
+            SyslogFacility::LOG_DAEMON,
+            SyslogFacility::LOG_AUTH,
+            SyslogFacility::LOG_SYSLOG,
+            SyslogFacility::LOG_LPR,
+            SyslogFacility::LOG_NEWS,
+            SyslogFacility::LOG_UUCP,
+            SyslogFacility::LOG_CRON,
+            SyslogFacility::LOG_AUTHPRIV,
+            SyslogFacility::LOG_FTP,
+            SyslogFacility::LOG_NTP,
+            SyslogFacility::LOG_AUDIT,
+            SyslogFacility::LOG_ALERT,
+            SyslogFacility::LOG_CLOCKD,
+            SyslogFacility::LOG_LOCAL0,
+            SyslogFacility::LOG_LOCAL1,
+            SyslogFacility::LOG_LOCAL2,
+            SyslogFacility::LOG_LOCAL3,
+            SyslogFacility::LOG_LOCAL4,
+            SyslogFacility::LOG_LOCAL5,
+            SyslogFacility::LOG_LOCAL6,
+            SyslogFacility::LOG_LOCAL7,
+        ]
+    }
+
     /// Convert an int (as used in the wire serialization) into a `SyslogFacility`
-    pub(crate) fn from_int(i: i32) -> Option<Self> {
+    pub fn from_int(i: i32) -> Option<Self> {
         match i {
             0 => Some(SyslogFacility::LOG_KERN),
             1 => Some(SyslogFacility::LOG_USER),
@@ -68,6 +104,16 @@ impl SyslogFacility {
         }
     }
 
+    /// The numeric code of the facility, as used in the wire serialization.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// The facility with the given numeric code, the reverse of `code`.
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::from_int(code as i32)
+    }
+
     /// Convert a syslog facility into a unique string representation
     pub fn as_str(self) -> &'static str {
         match self {
@@ -97,6 +143,20 @@ impl SyslogFacility {
             SyslogFacility::LOG_LOCAL7 => "local7",
         }
     }
+
+    /// Returns the string representations of all the facilities, in order.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        Self::all().iter().map(|facility| facility.as_str())
+    }
+
+    /// Convert the string representation returned by `as_str` back into a `SyslogFacility`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .find(|facility| facility.as_str() == name)
+            .copied()
+    }
 }
 
 // Taken from https://github.com/Roguelazer/rust-syslog-rfc5424/blob/af76363081314f91433e014c76fd834acef756d5/src/severity.rs
@@ -118,11 +178,25 @@ pub enum SyslogSeverity {
 }
 
 impl SyslogSeverity {
+    /// All the severities, in order of their numeric code.
+    pub fn all() -> &'static [SyslogSeverity] {
+        &[
+            SyslogSeverity::SEV_EMERG,
+            SyslogSeverity::SEV_ALERT,
+            SyslogSeverity::SEV_CRIT,
+            SyslogSeverity::SEV_ERR,
+            SyslogSeverity::SEV_WARNING,
+            SyslogSeverity::SEV_NOTICE,
+            SyslogSeverity::SEV_INFO,
+            SyslogSeverity::SEV_DEBUG,
+        ]
+    }
+
     /// Convert an int (as used in the wire serialization) into a `SyslogSeverity`
     ///
     /// Returns an Option, but the wire protocol will only include 0..7, so should
     /// never return None in practical usage.
-    pub(crate) fn from_int(i: i32) -> Option<Self> {
+    pub fn from_int(i: i32) -> Option<Self> {
         match i {
             0 => Some(SyslogSeverity::SEV_EMERG),
             1 => Some(SyslogSeverity::SEV_ALERT),
@@ -136,6 +210,16 @@ impl SyslogSeverity {
         }
     }
 
+    /// The numeric code of the severity, as used in the wire serialization.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// The severity with the given numeric code, the reverse of `code`.
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::from_int(code as i32)
+    }
+
     /// Convert a syslog severity into a unique string representation
     pub fn as_str(self) -> &'static str {
         match self {
@@ -149,17 +233,118 @@ impl SyslogSeverity {
             SyslogSeverity::SEV_DEBUG => "debug",
         }
     }
+
+    /// Returns the string representations of all the severities, in order.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        Self::all().iter().map(|severity| severity.as_str())
+    }
+
+    /// Convert the string representation returned by `as_str` back into a `SyslogSeverity`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .find(|severity| severity.as_str() == name)
+            .copied()
+    }
+
+    /// Convert a level word as used by common logging frameworks into a `SyslogSeverity`.
+    /// eg. `warn` maps to `SEV_WARNING` and `fatal` maps to `SEV_EMERG`.
+    /// The comparison is case insensitive.
+    pub fn from_level_word(word: &str) -> Option<Self> {
+        match word.to_lowercase().as_ref() {
+            "emerg" | "emergency" | "fatal" | "panic" => Some(SyslogSeverity::SEV_EMERG),
+            "alert" => Some(SyslogSeverity::SEV_ALERT),
+            "crit" | "critical" => Some(SyslogSeverity::SEV_CRIT),
+            "err" | "error" => Some(SyslogSeverity::SEV_ERR),
+            "warn" | "warning" => Some(SyslogSeverity::SEV_WARNING),
+            "notice" => Some(SyslogSeverity::SEV_NOTICE),
+            "info" | "information" => Some(SyslogSeverity::SEV_INFO),
+            "debug" | "trace" => Some(SyslogSeverity::SEV_DEBUG),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{SyslogFacility, SyslogSeverity};
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Facilities and severities are serialized using their `as_str` names.
+    macro_rules! serde_as_str {
+        ($ty:ident) => {
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(self.as_str())
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let name = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+                    $ty::from_str(&name).ok_or_else(|| {
+                        de::Error::invalid_value(de::Unexpected::Str(&name), &stringify!($ty))
+                    })
+                }
+            }
+        };
+    }
+
+    serde_as_str!(SyslogFacility);
+    serde_as_str!(SyslogSeverity);
+}
+
+/// Maps the numeric facility codes to facilities, for platforms whose numbering differs from
+/// the standard. Any code that hasn't been remapped gives its standard facility.
+///
+/// ```
+/// use syslog_loose::{FacilityMap, SyslogFacility};
+///
+/// let map = FacilityMap::default()
+///     .with(13, SyslogFacility::LOG_ALERT)
+///     .with(14, SyslogFacility::LOG_AUDIT);
+/// assert_eq!(map.get(13), Some(SyslogFacility::LOG_ALERT));
+/// assert_eq!(map.get(16), Some(SyslogFacility::LOG_LOCAL0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FacilityMap([Option<SyslogFacility>; 24]);
+
+impl FacilityMap {
+    /// Map the given code to the facility. Codes above 23 are ignored.
+    pub fn with(mut self, code: u8, facility: SyslogFacility) -> Self {
+        if let Some(entry) = self.0.get_mut(code as usize) {
+            *entry = Some(facility);
+        }
+        self
+    }
+
+    /// Returns the facility for the given code.
+    pub fn get(&self, code: u8) -> Option<SyslogFacility> {
+        self.0.get(code as usize).copied().flatten()
+    }
+}
+
+impl Default for FacilityMap {
+    fn default() -> Self {
+        let mut map = [None; 24];
+        for (entry, facility) in map.iter_mut().zip(SyslogFacility::all()) {
+            *entry = Some(*facility);
+        }
+        FacilityMap(map)
+    }
 }
 
 /// The pri field is composed of both the facility and severity values.
 /// The first byte is the Severity, the remaining are the Facility.
+/// A pri above 191 has no valid facility, so only the severity is returned.
 pub fn decompose_pri(pri: u8) -> (Option<SyslogFacility>, Option<SyslogSeverity>) {
     let facility = pri >> 3;
     let severity = pri & 0x7;
 
     (
-        SyslogFacility::from_int(facility as i32),
-        SyslogSeverity::from_int(severity as i32),
+        SyslogFacility::from_code(facility),
+        SyslogSeverity::from_code(severity),
     )
 }
 
@@ -168,13 +353,204 @@ pub(crate) fn compose_pri(facility: SyslogFacility, severity: SyslogSeverity) ->
     ((facility as i32) << 3) + (severity as i32)
 }
 
+/// Parse the message priority, an integer surrounded by <>.
+/// If there is no priority, `None` is returned and no input is consumed.
+///
+/// ```
+/// use syslog_loose::parsers::parse_pri;
+///
+/// assert_eq!(parse_pri("<34>1 2003-10-11T22:14:15.003Z"), Ok(("1 2003-10-11T22:14:15.003Z", Some(34))));
+/// assert_eq!(parse_pri("Oct 11 22:14:15"), Ok(("Oct 11 22:14:15", None)));
+/// ```
+pub fn parse_pri(input: &str) -> IResult<&str, Option<u8>> {
+    opt(delimited(tag("<"), digits, tag(">")))(input)
+}
+
+/// Parse the priority written in hexadecimal, eg. <0x22>
+fn parse_hex_pri(input: &str) -> IResult<&str, Option<u8>> {
+    opt(delimited(
+        tag("<"),
+        preceded(
+            tag_no_case("0x"),
+            map_res(hex_digit1, |hex| u8::from_str_radix(hex, 16)),
+        ),
+        tag(">"),
+    ))(input)
+}
+
+/// Parse a priority written without the angle brackets, eg. `34 Oct 11 22:14:15`, as sent
+/// by some legacy relays. The number must be followed by whitespace and then something other
+/// than a digit, so the version of an RFC5424 message without a priority isn't mistaken for it.
+fn parse_bare_pri(input: &str) -> IResult<&str, Option<u8>> {
+    opt(terminated(
+        verify(digits, |pri: &u8| *pri <= 191),
+        tuple((space1, peek(not(digit1)))),
+    ))(input)
+}
+
+/// Parse a priority too large to fit in a `u8`, eg. `<999>`, as sent by some misbehaving devices.
+/// Returns the severity from the lowest 3 bits, or `None` if the number is too large to parse.
+fn parse_large_pri(input: &str) -> IResult<&str, Option<SyslogSeverity>> {
+    delimited(
+        tag("<"),
+        map(digit1, |pri: &str| {
+            pri.parse::<u64>()
+                .ok()
+                .and_then(|pri| SyslogSeverity::from_int((pri & 0x7) as i32))
+        }),
+        tag(">"),
+    )(input)
+}
+
+/// Parse a sequence number prepended to the message by a forwarder, eg. `12345: <34>...`
+/// The sequence number must be followed by the priority.
+fn sequence_prefix(input: &str) -> IResult<&str, Option<&str>> {
+    opt(terminated(
+        digit1,
+        tuple((tag(":"), space0, peek(tag("<")))),
+    ))(input)
+}
+
 // The message priority. An integer surrounded by <>
 // This number contains both the facility and the severity.
-pub(crate) fn pri(input: &str) -> IResult<&str, (Option<SyslogFacility>, Option<SyslogSeverity>)> {
-    map(
-        opt(delimited(tag("<"), map(digits, decompose_pri), tag(">"))),
-        |pri| pri.unwrap_or((None, None)),
-    )(input)
+// An out of range priority is still consumed, but gives no facility.
+pub(crate) fn pri(
+    options: ParseOptions,
+) -> impl Fn(&str) -> IResult<&str, (Option<SyslogFacility>, Option<SyslogSeverity>)> {
+    move |input| {
+        let input = if options.skip_sequence_prefix {
+            sequence_prefix(input)?.0
+        } else {
+            input
+        };
+
+        let (input, pri) = match parse_pri(input)? {
+            (input, None) if options.hex_pri => parse_hex_pri(input)?,
+            parsed => parsed,
+        };
+        let (input, pri) = match pri {
+            None if options.bare_pri => parse_bare_pri(input)?,
+            pri => (input, pri),
+        };
+
+        let (input, (facility, severity)) = match pri {
+            Some(pri) => match options.facility_map {
+                Some(map) => (
+                    input,
+                    (map.get(pri >> 3), SyslogSeverity::from_code(pri & 0x7)),
+                ),
+                None => (input, decompose_pri(pri)),
+            },
+            None => match opt(parse_large_pri)(input)? {
+                (input, Some(severity)) => (input, (None, severity)),
+                (input, None) => (input, (None, None)),
+            },
+        };
+        let facility = match facility {
+            Some(SyslogFacility::LOG_KERN) if options.remap_kernel_facility => {
+                Some(SyslogFacility::LOG_USER)
+            }
+            facility => facility,
+        };
+
+        Ok((input, (facility, severity)))
+    }
+}
+
+#[test]
+fn test_bare_pri() {
+    let options = ParseOptions {
+        bare_pri: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        pri(options)("34 Oct 11 22:14:15"),
+        Ok((
+            "Oct 11 22:14:15",
+            (
+                Some(SyslogFacility::LOG_AUTH),
+                Some(SyslogSeverity::SEV_CRIT)
+            )
+        ))
+    );
+    assert_eq!(
+        pri(options)("<34>Oct 11 22:14:15"),
+        Ok((
+            "Oct 11 22:14:15",
+            (
+                Some(SyslogFacility::LOG_AUTH),
+                Some(SyslogSeverity::SEV_CRIT)
+            )
+        ))
+    );
+    assert_eq!(
+        pri(options)("1 2003-10-11T22:14:15.003Z"),
+        Ok(("1 2003-10-11T22:14:15.003Z", (None, None)))
+    );
+    assert_eq!(
+        pri(options)("192 Oct 11 22:14:15"),
+        Ok(("192 Oct 11 22:14:15", (None, None)))
+    );
+    assert_eq!(
+        pri(ParseOptions::default())("34 Oct 11 22:14:15"),
+        Ok(("34 Oct 11 22:14:15", (None, None)))
+    );
+}
+
+#[test]
+fn test_facility_map() {
+    let options = ParseOptions {
+        facility_map: Some(
+            FacilityMap::default()
+                .with(13, SyslogFacility::LOG_ALERT)
+                .with(14, SyslogFacility::LOG_CLOCKD)
+                .with(15, SyslogFacility::LOG_AUDIT)
+                .with(24, SyslogFacility::LOG_KERN),
+        ),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        pri(options)("<110>"),
+        Ok((
+            "",
+            (
+                Some(SyslogFacility::LOG_ALERT),
+                Some(SyslogSeverity::SEV_INFO)
+            )
+        ))
+    );
+    assert_eq!(
+        pri(options)("<126>"),
+        Ok((
+            "",
+            (
+                Some(SyslogFacility::LOG_AUDIT),
+                Some(SyslogSeverity::SEV_INFO)
+            )
+        ))
+    );
+    assert_eq!(
+        pri(options)("<34>"),
+        Ok((
+            "",
+            (
+                Some(SyslogFacility::LOG_AUTH),
+                Some(SyslogSeverity::SEV_CRIT)
+            )
+        ))
+    );
+    assert_eq!(
+        pri(ParseOptions::default())("<110>"),
+        Ok((
+            "",
+            (
+                Some(SyslogFacility::LOG_AUDIT),
+                Some(SyslogSeverity::SEV_INFO)
+            )
+        ))
+    );
 }
 
 #[test]
@@ -211,7 +587,7 @@ mod tests {
     #[test]
     fn parse_pri() {
         assert_eq!(
-            pri("<34>").unwrap(),
+            pri(ParseOptions::default())("<34>").unwrap(),
             (
                 "",
                 (
@@ -222,8 +598,162 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_out_of_range_pri() {
+        assert_eq!(
+            pri(ParseOptions::default())("<255>").unwrap(),
+            ("", (None, Some(SyslogSeverity::SEV_DEBUG)))
+        );
+        assert_eq!(
+            pri(ParseOptions::default())("<999>").unwrap(),
+            ("", (None, Some(SyslogSeverity::SEV_DEBUG)))
+        );
+        assert_eq!(
+            pri(ParseOptions::default())("<99999999999999999999>").unwrap(),
+            ("", (None, None))
+        );
+
+        let message =
+            crate::parse_message("<999>Oct 11 22:14:15 host app: msg", crate::Variant::Either);
+        assert_eq!(message.facility, None);
+        assert_eq!(message.severity, Some(SyslogSeverity::SEV_DEBUG));
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.msg, "msg");
+    }
+
     #[test]
     fn parse_missing_pri() {
-        assert_eq!(pri("1 xxx").unwrap(), ("1 xxx", (None, None)));
+        assert_eq!(
+            pri(ParseOptions::default())("1 xxx").unwrap(),
+            ("1 xxx", (None, None))
+        );
+    }
+
+    #[test]
+    fn parse_pri_remap_kernel() {
+        let options = ParseOptions {
+            remap_kernel_facility: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pri(options)("<2>").unwrap(),
+            (
+                "",
+                (
+                    Some(SyslogFacility::LOG_USER),
+                    Some(SyslogSeverity::SEV_CRIT)
+                )
+            )
+        );
+        assert_eq!(
+            pri(ParseOptions::default())("<2>").unwrap(),
+            (
+                "",
+                (
+                    Some(SyslogFacility::LOG_KERN),
+                    Some(SyslogSeverity::SEV_CRIT)
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn parse_pri_hex() {
+        let options = ParseOptions {
+            hex_pri: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pri(options)("<0x22>").unwrap(),
+            pri(ParseOptions::default())("<34>").unwrap()
+        );
+        assert_eq!(
+            pri(options)("<34>").unwrap(),
+            pri(options)("<0X22>").unwrap()
+        );
+
+        // Hex is only accepted when enabled.
+        assert_eq!(
+            pri(ParseOptions::default())("<0x22>").unwrap(),
+            ("<0x22>", (None, None))
+        );
+    }
+
+    #[test]
+    fn all() {
+        for (code, facility) in SyslogFacility::all().iter().enumerate() {
+            assert_eq!(SyslogFacility::from_int(code as i32), Some(*facility));
+            assert_eq!(SyslogFacility::from_str(facility.as_str()), Some(*facility));
+        }
+        for (code, severity) in SyslogSeverity::all().iter().enumerate() {
+            assert_eq!(SyslogSeverity::from_int(code as i32), Some(*severity));
+            assert_eq!(SyslogSeverity::from_str(severity.as_str()), Some(*severity));
+        }
+
+        assert_eq!(
+            SyslogFacility::from_str("local4"),
+            Some(SyslogFacility::LOG_LOCAL4)
+        );
+        assert_eq!(SyslogFacility::from_str("nope"), None);
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(SyslogFacility::LOG_KERN.code(), 0);
+        assert_eq!(SyslogFacility::LOG_LOCAL7.code(), 23);
+        assert_eq!(SyslogSeverity::SEV_EMERG.code(), 0);
+        assert_eq!(SyslogSeverity::SEV_DEBUG.code(), 7);
+    }
+
+    #[test]
+    fn from_code() {
+        for facility in SyslogFacility::all() {
+            assert_eq!(SyslogFacility::from_code(facility.code()), Some(*facility));
+        }
+        for severity in SyslogSeverity::all() {
+            assert_eq!(SyslogSeverity::from_code(severity.code()), Some(*severity));
+        }
+
+        assert_eq!(SyslogFacility::from_code(24), None);
+        assert_eq!(SyslogSeverity::from_code(8), None);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(SyslogFacility::names().count(), 24);
+        assert!(SyslogFacility::names().any(|name| name == "local7"));
+        assert_eq!(SyslogSeverity::names().next(), Some("emerg"));
+        assert_eq!(SyslogSeverity::names().last(), Some("debug"));
+    }
+
+    #[test]
+    fn severity_from_level_word() {
+        assert_eq!(
+            SyslogSeverity::from_level_word("warn"),
+            Some(SyslogSeverity::SEV_WARNING)
+        );
+        assert_eq!(
+            SyslogSeverity::from_level_word("WARNING"),
+            Some(SyslogSeverity::SEV_WARNING)
+        );
+        assert_eq!(
+            SyslogSeverity::from_level_word("fatal"),
+            Some(SyslogSeverity::SEV_EMERG)
+        );
+        assert_eq!(
+            SyslogSeverity::from_level_word("panic"),
+            Some(SyslogSeverity::SEV_EMERG)
+        );
+        assert_eq!(
+            SyslogSeverity::from_level_word("error"),
+            Some(SyslogSeverity::SEV_ERR)
+        );
+        assert_eq!(
+            SyslogSeverity::from_level_word("trace"),
+            Some(SyslogSeverity::SEV_DEBUG)
+        );
+        assert_eq!(SyslogSeverity::from_level_word("verbose"), None);
     }
 }
@@ -1,9 +1,10 @@
-use std::fmt;
+use std::{fmt, ops::RangeBounds};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::upper_case_acronyms)]
 pub enum ProcId<S: AsRef<str> + Ord + PartialEq + Clone> {
-    PID(i32),
+    PID(i64),
     Name(S),
 }
 
@@ -16,6 +17,35 @@ impl<S: AsRef<str> + Ord + PartialEq + Clone> fmt::Display for ProcId<S> {
     }
 }
 
+impl<S: AsRef<str> + Ord + PartialEq + Clone> ProcId<S> {
+    /// Returns the pid if the procid is numeric.
+    pub fn pid(&self) -> Option<i64> {
+        match self {
+            ProcId::PID(pid) => Some(*pid),
+            ProcId::Name(_) => None,
+        }
+    }
+
+    /// Returns true if the procid is a pid within the given range.
+    pub fn in_range<R: RangeBounds<i64>>(&self, range: R) -> bool {
+        self.pid().is_some_and(|pid| range.contains(&pid))
+    }
+}
+
+impl<S: AsRef<str> + Ord + PartialEq + Clone> ProcId<S> {
+    /// Convert the name to another string type.
+    pub(crate) fn map_str<T, F>(self, f: F) -> ProcId<T>
+    where
+        T: AsRef<str> + Ord + PartialEq + Clone,
+        F: FnOnce(S) -> T,
+    {
+        match self {
+            ProcId::PID(pid) => ProcId::PID(pid),
+            ProcId::Name(name) => ProcId::Name(f(name)),
+        }
+    }
+}
+
 impl From<ProcId<&str>> for ProcId<String> {
     fn from(procid: ProcId<&str>) -> Self {
         match procid {
@@ -25,6 +55,8 @@ impl From<ProcId<&str>> for ProcId<String> {
     }
 }
 
+/// A procid that is an integer becomes a `PID`. Anything else, including a number
+/// too large to fit in an `i64`, is kept as a `Name`.
 impl<'a> From<&'a str> for ProcId<&'a str> {
     fn from(s: &str) -> ProcId<&str> {
         match s.parse() {
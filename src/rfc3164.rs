@@ -1,19 +1,21 @@
 //! Parsers for rfc 3164 specific formats.
 use crate::{
     message::{Message, Protocol},
-    parsers::{hostname, tagname},
+    options::{HeaderBoundary, ParseOptions},
+    parsers::{hostname, msg_separator, tagname, tagname_with_spaces},
     pri::pri,
     structured_data::structured_data_optional,
-    timestamp::{timestamp_3164, IncompleteDate},
+    timestamp::{bracketed_timestamp, timestamp_3164, IncompleteDate},
 };
 use chrono::prelude::*;
 use nom::{
-    bytes::complete::{is_not, tag, take_while},
-    character::complete::space0,
-    combinator::{map, opt, rest},
-    sequence::{delimited, preceded, tuple},
+    bytes::complete::{is_not, tag, take_until, take_while},
+    character::complete::{digit1, one_of, space0, space1},
+    combinator::{consumed, eof, map, map_parser, opt, rest},
+    sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
+use std::net::IpAddr;
 
 // Parse the tag - a process name followed by a pid in [].
 pub(crate) fn systag(input: &str) -> IResult<&str, (&str, &str)> {
@@ -23,9 +25,23 @@ pub(crate) fn systag(input: &str) -> IResult<&str, (&str, &str)> {
     ))(input)
 }
 
+/// Split the tag into the appname and procid if it follows the format appname[procid].
+fn split_tag(tag: &str) -> Option<(&str, &str)> {
+    // Only run the parser if the tag could possibly contain a procid.
+    if !tag.ends_with(']') {
+        return None;
+    }
+
+    match systag(tag) {
+        Ok(("", parsed)) => Some(parsed),
+        _ => None,
+    }
+}
+
 /// Resolves the final two potential fields in the header.
 /// Sometimes, there is only one field, this may be the host or the tag.
-/// We can determine if this field is the tag only if it follows the format appname[procid].
+/// We can determine if this field is the tag if it follows the format appname[procid],
+/// or if it is immediately followed by the `:` separator (and isn't an ip address).
 ///
 /// Each field has three potential states :
 ///   None => Means the field hasnt been specified at all.
@@ -34,24 +50,26 @@ pub(crate) fn systag(input: &str) -> IResult<&str, (&str, &str)> {
 fn resolve_host_and_tag<'a>(
     field1: Option<Option<&'a str>>,
     field2: Option<Option<&'a str>>,
+    colon_attached: bool,
 ) -> (Option<&'a str>, Option<&'a str>, Option<&'a str>) {
     match (field1, field2) {
         // Both field specified, tag just needs parsing to see if there is a procid
-        (Some(host), Some(Some(tag))) => match systag(tag) {
-            Ok(("", (app, procid))) => (host, Some(app), Some(procid)),
-            _ => (host, Some(tag), None),
+        (Some(host), Some(Some(tag))) => match split_tag(tag) {
+            Some((app, procid)) => (host, Some(app), Some(procid)),
+            None => (host, Some(tag), None),
         },
 
         // Only one field specified, is this the host or the tag?
-        (Some(Some(field)), None) => match systag(field) {
-            Ok(("", (app, procid))) => (None, Some(app), Some(procid)),
-            _ => (Some(field), None, None),
+        (Some(Some(field)), None) => match split_tag(field) {
+            Some((app, procid)) => (None, Some(app), Some(procid)),
+            None if colon_attached && field.parse::<IpAddr>().is_err() => (None, Some(field), None),
+            None => (Some(field), None, None),
         },
 
         // This one should never happen, but just for completeness...
-        (None, Some(Some(field))) => match systag(field) {
-            Ok(("", (app, procid))) => (None, Some(app), Some(procid)),
-            _ => (Some(field), None, None),
+        (None, Some(Some(field))) => match split_tag(field) {
+            Some((app, procid)) => (None, Some(app), Some(procid)),
+            None => (Some(field), None, None),
         },
 
         // No field specified.
@@ -59,42 +77,173 @@ fn resolve_host_and_tag<'a>(
     }
 }
 
+/// Parse the tag field, optionally allowing it to contain spaces.
+fn tag_field(spaces_in_tag: bool) -> fn(&str) -> IResult<&str, Option<&str>> {
+    if spaces_in_tag {
+        tagname_with_spaces
+    } else {
+        tagname
+    }
+}
+
+/// Parse the space preceding the first header field.
+fn first_space(
+    header_boundary: HeaderBoundary,
+    leading_space: bool,
+) -> fn(&str) -> IResult<&str, &str> {
+    match (leading_space, header_boundary) {
+        (false, _) => |input| Ok((input, "")),
+        (true, HeaderBoundary::Colon) => |input| space1(input),
+        (true, _) => |input| tag(" ")(input),
+    }
+}
+
+/// Take everything up to the header boundary, which is a run of two spaces or a tab.
+fn until_two_spaces_or_tab(input: &str) -> IResult<&str, &str> {
+    match [input.find("  "), input.find('\t')]
+        .iter()
+        .flatten()
+        .min()
+        .copied()
+    {
+        Some(idx) => Ok((&input[idx..], &input[..idx])),
+        None => take_until("  ")(input),
+    }
+}
+
+type HeaderFields<'a> = (Option<Option<&'a str>>, Option<Option<&'a str>>);
+
+/// Parse the two potential header fields that follow the timestamp.
+/// How far these fields can extend is determined by the `HeaderBoundary`.
+/// If there is no timestamp, `leading_space` is false as the first field starts immediately.
+fn header_fields(
+    options: ParseOptions,
+    leading_space: bool,
+) -> impl FnMut(&str) -> IResult<&str, HeaderFields> {
+    let tag_field = tag_field(options.spaces_in_tag);
+    let first_space = first_space(options.header_boundary, leading_space);
+
+    move |input| match options.header_boundary {
+        HeaderBoundary::Either => tuple((
+            opt(preceded(first_space, hostname)),
+            opt(preceded(tag(" "), tag_field)),
+        ))(input),
+        HeaderBoundary::Colon => tuple((
+            opt(preceded(first_space, hostname)),
+            opt(preceded(space1, tag_field)),
+        ))(input),
+        HeaderBoundary::TwoSpaces => map_parser(
+            until_two_spaces_or_tab,
+            terminated(
+                tuple((
+                    opt(preceded(first_space, hostname)),
+                    opt(preceded(tag(" "), tag_field)),
+                )),
+                tuple((opt(tag(":")), eof)),
+            ),
+        )(input),
+    }
+}
+
+type RawTimestamp<'a> = (&'a str, DateTime<FixedOffset>);
+
+/// Parse the timestamp, along with the text it was parsed from, and the header fields that follow it.
+/// If the `bracketed_timestamp` option is set the timestamp is optional.
+fn timestamp_and_header<F, Tz: TimeZone + Copy>(
+    get_year: F,
+    tz: Option<Tz>,
+    options: ParseOptions,
+) -> impl FnMut(&str) -> IResult<&str, (Option<RawTimestamp>, HeaderFields)>
+where
+    F: FnOnce(IncompleteDate) -> i32 + Copy,
+{
+    move |input| match consumed(timestamp_3164(get_year, tz, options))(input) {
+        Ok((input, (raw, timestamp))) => map(header_fields(options, true), |header| {
+            // The timestamp parser also takes any colon following the seconds.
+            (Some((raw.trim_end_matches(':'), timestamp)), header)
+        })(input),
+        Err(_) if options.bracketed_timestamp => {
+            map(header_fields(options, false), |header| (None, header))(input)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Cisco IOS devices can prefix the timestamp with a sequence number, eg. `42: `, and mark
+/// the timestamp with a `*` if the clock is not synchronized or a `.` if it has lost sync.
+/// eg. `<189>42: *Mar  1 00:01:02.345: %SYS-5-CONFIG_I: msg`. These are skipped over.
+fn cisco_prefix(input: &str) -> IResult<&str, ()> {
+    map(
+        tuple((
+            opt(terminated(digit1, tuple((tag(":"), space1)))),
+            opt(one_of("*.")),
+        )),
+        |_| (),
+    )(input)
+}
+
 /// Parses the message as per RFC3164.
 pub fn parse<F, Tz: TimeZone + Copy>(
     input: &str,
     get_year: F,
     tz: Option<Tz>,
+    options: ParseOptions,
 ) -> IResult<&str, Message<&str>>
 where
     F: FnOnce(IncompleteDate) -> i32 + Copy,
 {
     map(
         tuple((
-            pri,
-            opt(space0),
-            timestamp_3164(get_year, tz),
-            opt(preceded(tag(" "), hostname)),
-            opt(preceded(tag(" "), tagname)),
+            pri(options),
             opt(space0),
+            cisco_prefix,
+            timestamp_and_header(get_year, tz, options),
+            space0,
             opt(tag(":")),
-            opt(space0),
-            opt(structured_data_optional(false)),
-            opt(space0),
+            msg_separator(options.keep_msg_whitespace),
+            opt(terminated(
+                structured_data_optional(false, options),
+                msg_separator(options.keep_msg_whitespace),
+            )),
             rest,
         )),
-        |(pri, _, timestamp, field1, field2, _, _, _, structured_data, _, msg)| {
-            let (host, appname, pid) = resolve_host_and_tag(field1, field2);
+        |(pri, _, _, (timestamp, (field1, field2)), space, colon, _, structured_data, msg)| {
+            let colon_attached = space.is_empty() && colon.is_some();
+            let (host, appname, pid) = resolve_host_and_tag(field1, field2, colon_attached);
+
+            let (msg, timestamp) = match timestamp {
+                None if options.bracketed_timestamp => {
+                    match consumed(bracketed_timestamp(tz))(msg) {
+                        Ok((msg, (raw, timestamp))) => {
+                            let raw = raw.trim_end().trim_start_matches('[').trim_end_matches(']');
+                            (msg, Some((raw, timestamp)))
+                        }
+                        Err(_) => (msg, None),
+                    }
+                }
+                timestamp => (msg, timestamp),
+            };
+
+            let had_bom = msg.starts_with('\u{feff}');
+            let msg = if options.keep_bom {
+                msg
+            } else {
+                msg.strip_prefix('\u{feff}').unwrap_or(msg)
+            };
 
             Message {
                 protocol: Protocol::RFC3164,
                 facility: pri.0,
                 severity: pri.1,
-                timestamp: Some(timestamp),
+                timestamp: timestamp.map(|(_, timestamp)| timestamp),
+                raw_timestamp: timestamp.map(|(raw, _)| raw),
                 hostname: host,
                 appname,
                 procid: pid.map(|p| p.into()),
                 msgid: None,
                 structured_data: structured_data.unwrap_or_default(),
+                unparsed_structured_data: vec![],
+                had_bom,
                 msg,
             }
         },
@@ -127,7 +276,13 @@ mod tests {
         This is not completely compliant with the RFC.
         */
         assert_eq!(
-            parse("<34>Oct 11 22:14:15 : a message", |_| 2019, Some(Utc.fix())).unwrap(),
+            parse(
+                "<34>Oct 11 22:14:15 : a message",
+                |_| 2019,
+                Some(Utc.fix()),
+                ParseOptions::default()
+            )
+            .unwrap(),
             (
                 "",
                 Message {
@@ -139,11 +294,14 @@ mod tests {
                             .unwrap()
                             .into()
                     ),
+                    raw_timestamp: Some("Oct 11 22:14:15"),
                     hostname: None,
                     appname: None,
                     procid: None,
                     msgid: None,
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: "a message",
                 }
             )
@@ -158,7 +316,7 @@ mod tests {
         let msg = r#"<134>Oct 30 16:05:54 opsaudit  {\"username\": \"admin\", \"ip\": \"7.7.7.7\", \"type\": \"\", \"user_agent\": \"Go-http-client/1.1\", \"datetime\": \"2020-10-30 16:05:45\", \"mfa\": 0, \"status\": true, \"city\": \"局域网\", \"optype\": \"user-login\"}"#;
 
         assert_eq!(
-            parse(msg, |_| 2020, Some(Utc.fix())).unwrap(),
+            parse(msg, |_| 2020, Some(Utc.fix()), ParseOptions::default()).unwrap(),
             (
                 "",
                 Message {
@@ -169,12 +327,15 @@ mod tests {
                             .unwrap()
                             .into()
                     ),
+                    raw_timestamp: Some("Oct 30 16:05:54"),
                     hostname: Some("opsaudit"),
                     appname: None,
                     procid: None,
                     msgid: None,
                     protocol: Protocol::RFC3164,
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: r#"{\"username\": \"admin\", \"ip\": \"7.7.7.7\", \"type\": \"\", \"user_agent\": \"Go-http-client/1.1\", \"datetime\": \"2020-10-30 16:05:45\", \"mfa\": 0, \"status\": true, \"city\": \"局域网\", \"optype\": \"user-login\"}"#,
                 }
             )
@@ -184,8 +345,13 @@ mod tests {
     #[test]
     fn parse_3164_timestamp_uppercase() {
         assert_eq!(
-            parse::<_, FixedOffset>("<34>OCT 11 22:14:15 : a message", |_| 2019, Some(Utc.fix()))
-                .unwrap(),
+            parse::<_, FixedOffset>(
+                "<34>OCT 11 22:14:15 : a message",
+                |_| 2019,
+                Some(Utc.fix()),
+                ParseOptions::default()
+            )
+            .unwrap(),
             (
                 "",
                 Message {
@@ -197,11 +363,14 @@ mod tests {
                             .unwrap()
                             .into()
                     ),
+                    raw_timestamp: Some("OCT 11 22:14:15"),
                     hostname: None,
                     appname: None,
                     procid: None,
                     msgid: None,
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: "a message",
                 }
             )
@@ -214,7 +383,8 @@ mod tests {
             parse::<_, FixedOffset>(
                 "<34>Oct 11 22:14:15 mymachine: a message",
                 |_| 2019,
-                Some(Utc.fix())
+                Some(Utc.fix()),
+                ParseOptions::default()
             )
             .unwrap(),
             (
@@ -228,11 +398,14 @@ mod tests {
                             .unwrap()
                             .into()
                     ),
-                    hostname: Some("mymachine"),
-                    appname: None,
+                    raw_timestamp: Some("Oct 11 22:14:15"),
+                    hostname: None,
+                    appname: Some("mymachine"),
                     procid: None,
                     msgid: None,
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: "a message",
                 }
             )
@@ -242,7 +415,13 @@ mod tests {
     #[test]
     fn parse_3164_host_with_space() {
         assert_eq!(
-            parse::<_, Utc>("<54> 1970-01-01T00:01:31+00:00 host :", |_| 2019, None).unwrap(),
+            parse::<_, Utc>(
+                "<54> 1970-01-01T00:01:31+00:00 host :",
+                |_| 2019,
+                None,
+                ParseOptions::default()
+            )
+            .unwrap(),
             (
                 "",
                 Message {
@@ -250,11 +429,14 @@ mod tests {
                     facility: Some(SyslogFacility::LOG_LPR,),
                     severity: Some(SyslogSeverity::SEV_INFO,),
                     timestamp: Some(Utc.with_ymd_and_hms(1970, 1, 1, 0, 1, 31).unwrap().into()),
+                    raw_timestamp: Some("1970-01-01T00:01:31+00:00"),
                     hostname: Some("host",),
                     appname: None,
                     procid: None,
                     msgid: None,
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: "",
                 }
             )
@@ -267,7 +449,8 @@ mod tests {
             parse::<_, FixedOffset>(
                 "<34>Oct 11 22:14:15 mymachine app[323]: a message",
                 |_| { 2019 },
-                Some(Utc.fix())
+                Some(Utc.fix()),
+                ParseOptions::default()
             )
             .unwrap(),
             (
@@ -281,11 +464,14 @@ mod tests {
                             .unwrap()
                             .into()
                     ),
+                    raw_timestamp: Some("Oct 11 22:14:15"),
                     hostname: Some("mymachine"),
                     appname: Some("app"),
                     procid: Some(ProcId::PID(323)),
                     msgid: None,
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: "a message",
                 }
             )
@@ -298,7 +484,8 @@ mod tests {
             parse::<_, Local>(
                 "<34>2020-10-11T22:14:15.00Z mymachine app[323]: a message",
                 |_| { 2019 },
-                None
+                None,
+                ParseOptions::default()
             )
             .unwrap(),
             (
@@ -313,11 +500,14 @@ mod tests {
                             .with_ymd_and_hms(2020, 10, 11, 22, 14, 15)
                             .unwrap()
                     ),
+                    raw_timestamp: Some("2020-10-11T22:14:15.00Z"),
                     hostname: Some("mymachine"),
                     appname: Some("app"),
                     procid: Some(ProcId::PID(323)),
                     msgid: None,
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: "a message",
                 }
             )
@@ -330,7 +520,8 @@ mod tests {
             parse::<_, FixedOffset>(
                 "<131>Jun 8 11:54:08 master apache_error [Tue Jun 08 11:54:08.929301 2021] [php7:emerg] [pid 1374899] [client 95.223.77.60:41888] rest of message",
                 |_| { 2021 },
-                Some(Utc.fix())
+                Some(Utc.fix()),
+                ParseOptions::default()
             )
             .unwrap(),
             (
@@ -340,14 +531,315 @@ mod tests {
                     facility: Some(SyslogFacility::LOG_LOCAL0),
                     severity: Some(SyslogSeverity::SEV_ERR),
                     timestamp: Some(FixedOffset::west_opt(0).unwrap().with_ymd_and_hms(2021, 6, 8,11, 54, 8).unwrap()),
+                    raw_timestamp: Some("Jun 8 11:54:08"),
                     hostname: Some("master"),
                     appname: Some("apache_error"),
                     procid: None,
                     msgid: None,
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: "[Tue Jun 08 11:54:08.929301 2021] [php7:emerg] [pid 1374899] [client 95.223.77.60:41888] rest of message",
                 }
             )
         );
     }
+
+    #[test]
+    fn parse_3164_header_boundary_colon() {
+        let msg = r#"<134>Oct 30 16:05:54 opsaudit  app: {"username": "admin"}"#;
+
+        assert_eq!(
+            parse(
+                msg,
+                |_| 2020,
+                Some(Utc.fix()),
+                ParseOptions {
+                    header_boundary: HeaderBoundary::Colon,
+                    ..Default::default()
+                }
+            )
+            .unwrap(),
+            (
+                "",
+                Message {
+                    facility: Some(SyslogFacility::LOG_LOCAL0),
+                    severity: Some(SyslogSeverity::SEV_INFO),
+                    timestamp: Some(
+                        Utc.with_ymd_and_hms(2020, 10, 30, 16, 5, 54)
+                            .unwrap()
+                            .into()
+                    ),
+                    raw_timestamp: Some("Oct 30 16:05:54"),
+                    hostname: Some("opsaudit"),
+                    appname: Some("app"),
+                    procid: None,
+                    msgid: None,
+                    protocol: Protocol::RFC3164,
+                    structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
+                    msg: r#"{"username": "admin"}"#,
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_3164_header_boundary_two_spaces() {
+        let msg = r#"<134>Oct 30 16:05:54 opsaudit  app: {"username": "admin"}"#;
+        let options = ParseOptions {
+            header_boundary: HeaderBoundary::TwoSpaces,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse(msg, |_| 2020, Some(Utc.fix()), options).unwrap(),
+            (
+                "",
+                Message {
+                    facility: Some(SyslogFacility::LOG_LOCAL0),
+                    severity: Some(SyslogSeverity::SEV_INFO),
+                    timestamp: Some(
+                        Utc.with_ymd_and_hms(2020, 10, 30, 16, 5, 54)
+                            .unwrap()
+                            .into()
+                    ),
+                    raw_timestamp: Some("Oct 30 16:05:54"),
+                    hostname: Some("opsaudit"),
+                    appname: None,
+                    procid: None,
+                    msgid: None,
+                    protocol: Protocol::RFC3164,
+                    structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
+                    msg: r#"app: {"username": "admin"}"#,
+                }
+            )
+        );
+
+        // Without the two spaces there is nothing to mark the end of the header.
+        assert!(parse(
+            r#"<134>Oct 30 16:05:54 opsaudit app: {"username": "admin"}"#,
+            |_| 2020,
+            Some(Utc.fix()),
+            options
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_3164_tag_with_spaces() {
+        let msg = "<13>Feb 13 20:07:26 host my daemon: msg";
+        let options = ParseOptions {
+            spaces_in_tag: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse(msg, |_| 2020, Some(Utc.fix()), options).unwrap(),
+            (
+                "",
+                Message {
+                    facility: Some(SyslogFacility::LOG_USER),
+                    severity: Some(SyslogSeverity::SEV_NOTICE),
+                    timestamp: Some(Utc.with_ymd_and_hms(2020, 2, 13, 20, 7, 26).unwrap().into()),
+                    raw_timestamp: Some("Feb 13 20:07:26"),
+                    hostname: Some("host"),
+                    appname: Some("my daemon"),
+                    procid: None,
+                    msgid: None,
+                    protocol: Protocol::RFC3164,
+                    structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
+                    msg: "msg",
+                }
+            )
+        );
+
+        // By default the tag stops at the first space.
+        assert_eq!(
+            parse(msg, |_| 2020, Some(Utc.fix()), ParseOptions::default())
+                .unwrap()
+                .1
+                .appname,
+            Some("my")
+        );
+    }
+
+    #[test]
+    fn parse_3164_single_field_with_colon_is_tag() {
+        assert_eq!(
+            parse::<_, FixedOffset>(
+                "<34>Oct 11 22:14:15 app: msg",
+                |_| 2019,
+                Some(Utc.fix()),
+                ParseOptions::default()
+            )
+            .unwrap(),
+            (
+                "",
+                Message {
+                    protocol: Protocol::RFC3164,
+                    facility: Some(SyslogFacility::LOG_AUTH),
+                    severity: Some(SyslogSeverity::SEV_CRIT),
+                    timestamp: Some(
+                        Utc.with_ymd_and_hms(2019, 10, 11, 22, 14, 15)
+                            .unwrap()
+                            .into()
+                    ),
+                    raw_timestamp: Some("Oct 11 22:14:15"),
+                    hostname: None,
+                    appname: Some("app"),
+                    procid: None,
+                    msgid: None,
+                    structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
+                    msg: "msg",
+                }
+            )
+        );
+
+        // An ip address is still taken to be the host.
+        let (_, message) = parse::<_, FixedOffset>(
+            "<34>Oct 11 22:14:15 192.168.0.1: msg",
+            |_| 2019,
+            Some(Utc.fix()),
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, Some("192.168.0.1"));
+        assert_eq!(message.appname, None);
+    }
+
+    #[test]
+    fn parse_3164_bracketed_timestamp() {
+        let msg = "<13>host app: [2021-06-08 11:54:08] hello";
+        let options = ParseOptions {
+            bracketed_timestamp: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse(msg, |_| 2020, Some(Utc.fix()), options).unwrap(),
+            (
+                "",
+                Message {
+                    facility: Some(SyslogFacility::LOG_USER),
+                    severity: Some(SyslogSeverity::SEV_NOTICE),
+                    timestamp: Some(Utc.with_ymd_and_hms(2021, 6, 8, 11, 54, 8).unwrap().into()),
+                    raw_timestamp: Some("2021-06-08 11:54:08"),
+                    hostname: Some("host"),
+                    appname: Some("app"),
+                    procid: None,
+                    msgid: None,
+                    protocol: Protocol::RFC3164,
+                    structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
+                    msg: "hello",
+                }
+            )
+        );
+
+        // The syslog timestamp takes precedence.
+        let (_, message) = parse(
+            "<13>Feb 13 20:07:26 host app: [2021-06-08 11:54:08] hello",
+            |_| 2020,
+            Some(Utc.fix()),
+            options,
+        )
+        .unwrap();
+        assert_eq!(
+            message.timestamp,
+            Some(Utc.with_ymd_and_hms(2020, 2, 13, 20, 7, 26).unwrap().into())
+        );
+        assert_eq!(message.msg, "[2021-06-08 11:54:08] hello");
+
+        // By default a timestamp is required.
+        assert!(parse(msg, |_| 2020, Some(Utc.fix()), ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn parse_3164_tab_separator() {
+        let (_, message) = parse(
+            "<13>Feb 13 20:07:26 host app:\tmsg",
+            |_| 2020,
+            Some(Utc.fix()),
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.msg, "msg");
+
+        let options = ParseOptions {
+            header_boundary: HeaderBoundary::TwoSpaces,
+            ..Default::default()
+        };
+        let (_, message) = parse(
+            "<13>Feb 13 20:07:26 host app\tmsg: with colon",
+            |_| 2020,
+            Some(Utc.fix()),
+            options,
+        )
+        .unwrap();
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.msg, "msg: with colon");
+    }
+
+    #[test]
+    fn parse_3164_cisco_sequence_number() {
+        let (_, message) = parse(
+            "<189>42: *Mar  1 00:01:02.345: %SYS-5-CONFIG_I: Configured from console by console",
+            |_| 2020,
+            Some(Utc.fix()),
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.facility, Some(SyslogFacility::LOG_LOCAL7));
+        assert_eq!(message.severity, Some(SyslogSeverity::SEV_NOTICE));
+        assert_eq!(
+            message.timestamp,
+            Some(
+                Utc.fix().with_ymd_and_hms(2020, 3, 1, 0, 1, 2).unwrap()
+                    + chrono::Duration::milliseconds(345)
+            )
+        );
+        assert_eq!(message.hostname, None);
+        assert_eq!(message.appname, Some("%SYS-5-CONFIG_I"));
+        assert_eq!(message.msg, "Configured from console by console");
+
+        let (_, message) = parse(
+            "<189>.Mar  1 00:01:02: %SYS-5-CONFIG_I: msg",
+            |_| 2020,
+            Some(Utc.fix()),
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.appname, Some("%SYS-5-CONFIG_I"));
+        assert_eq!(message.msg, "msg");
+    }
+
+    #[test]
+    fn parse_3164_bom() {
+        let msg = "<34>Oct 11 22:14:15 host app: \u{feff}An application event";
+
+        let (_, message) = parse(msg, |_| 2019, Some(Utc.fix()), ParseOptions::default()).unwrap();
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.msg, "An application event");
+        assert!(message.had_bom);
+
+        let options = ParseOptions {
+            keep_bom: true,
+            ..Default::default()
+        };
+        let (_, message) = parse(msg, |_| 2019, Some(Utc.fix()), options).unwrap();
+        assert_eq!(message.msg, "\u{feff}An application event");
+        assert!(message.had_bom);
+    }
 }
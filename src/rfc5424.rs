@@ -1,72 +1,217 @@
 //! Parsers for rfc 5424 specific formats.
 use crate::{
     message::{Message, Protocol},
-    parsers::{appname, digits, hostname, msgid, procid},
+    options::ParseOptions,
+    parsers::{appname, hostname, msg_separator, msgid, procid},
     pri::pri,
-    structured_data::structured_data,
+    structured_data::{
+        structured_data, structured_data_optional, StructuredDataWithUnparsed, StructuredElement,
+    },
     timestamp::timestamp_3339,
 };
 use nom::{
-    character::complete::{space0, space1},
-    combinator::{map, rest},
-    sequence::tuple,
+    branch::alt,
+    bytes::complete::take_while_m_n,
+    character::complete::{none_of, one_of, space0, space1},
+    combinator::{consumed, map, map_res, not, opt, peek, rest, verify},
+    sequence::{preceded, terminated, tuple},
     IResult,
 };
 
-/// Parse the version number - just a simple integer.
+/// Parse the version number - an integer from 1 to 999 with no leading zeros,
+/// as per the NONZERO-DIGIT 0*2DIGIT rule.
 fn version(input: &str) -> IResult<&str, u32> {
-    digits(input)
+    preceded(
+        peek(none_of("0")),
+        map_res(
+            take_while_m_n(1, 3, |c: char| c.is_ascii_digit()),
+            str::parse,
+        ),
+    )(input)
 }
 
-/// Parse the message as per RFC5424
-pub(crate) fn parse(input: &str) -> IResult<&str, Message<&str>> {
-    map(
+/// Find any structured data that has been placed at the end of the message instead of
+/// before it. Returns the message with the structured data removed and the structured data.
+fn trailing_structured_data(
+    msg: &str,
+    options: ParseOptions,
+) -> Option<(&str, Vec<StructuredElement<&str>>)> {
+    msg.match_indices('[')
+        .filter(|(idx, _)| *idx == 0 || msg[..*idx].ends_with(' '))
+        .find_map(
+            |(idx, _)| match structured_data_optional(false, options)(&msg[idx..]) {
+                Ok(("", structured_data)) => Some((msg[..idx].trim_end(), structured_data)),
+                _ => None,
+            },
+        )
+}
+
+/// Split an appname of the form `name:digits` into the name and the digits.
+fn split_appname_procid(appname: &str) -> Option<(&str, &str)> {
+    let (name, procid) = appname.rsplit_once(':')?;
+    if !name.is_empty() && !procid.is_empty() && procid.bytes().all(|c| c.is_ascii_digit()) {
+        Some((name, procid))
+    } else {
+        None
+    }
+}
+
+/// Parse the hostname and the space following it.
+/// Some broken senders skip the hostname field entirely, in which case `missing` is true
+/// and nothing is parsed.
+fn hostname_field(missing: bool) -> fn(&str) -> IResult<&str, Option<&str>> {
+    if missing {
+        |input| Ok((input, None))
+    } else {
+        |input| terminated(hostname, space1)(input)
+    }
+}
+
+/// Parse the structured data, or, if `missing` is true, check that it has been left out
+/// and the message follows straight on, eg. a JSON body.
+fn structured_data_field(
+    missing: bool,
+    options: ParseOptions,
+) -> impl FnMut(&str) -> IResult<&str, StructuredDataWithUnparsed> {
+    move |input| {
+        if missing {
+            map(peek(none_of("-[")), |_| (vec![], vec![]))(input)
+        } else {
+            structured_data(options)(input)
+        }
+    }
+}
+
+/// Parse all the header fields, including the hostname, up to the start of the structured
+/// data, `-` or `[`. When this succeeds the header isn't missing a field, so a failure to
+/// parse the message is down to the structured data rather than a missing hostname.
+fn full_header(input: &str, options: ParseOptions) -> IResult<&str, char> {
+    preceded(
         tuple((
-            pri,
+            pri(options),
             version,
             space1,
             timestamp_3339,
             space1,
-            hostname,
-            space1,
+            hostname_field(false),
             appname,
             space1,
             procid,
             space1,
             msgid,
             space0,
-            structured_data,
+        )),
+        peek(one_of("-[")),
+    )(input)
+}
+
+/// Parse the message as per RFC5424
+/// If the message doesn't parse, try again assuming the structured data is missing, and
+/// then assuming the hostname field is missing, eg. `<34>1 2003-10-11T22:14:15Z app - - msg`.
+pub(crate) fn parse(input: &str, options: ParseOptions) -> IResult<&str, Message<&str>> {
+    alt((
+        |input| parse_fields(input, false, false, options),
+        // With the structured data missing, a nil appname suggests the hostname was
+        // skipped instead, so `app - - hello world` is left for the next parser.
+        verify(
+            |input| parse_fields(input, false, true, options),
+            |message| message.appname.is_some(),
+        ),
+        preceded(not(|input| full_header(input, options)), |input| {
+            parse_fields(input, true, false, options)
+        }),
+    ))(input)
+}
+
+fn parse_fields(
+    input: &str,
+    missing_hostname: bool,
+    missing_structured_data: bool,
+    options: ParseOptions,
+) -> IResult<&str, Message<&str>> {
+    map(
+        tuple((
+            verify(pri(options), |(facility, severity)| {
+                !options.require_pri || facility.is_some() || severity.is_some()
+            }),
+            version,
+            space1,
+            consumed(timestamp_3339),
+            space1,
+            hostname_field(missing_hostname),
+            appname,
+            space1,
+            procid,
+            space1,
+            msgid,
             space0,
+            |input| {
+                if missing_hostname {
+                    // A sender that skips the hostname may well skip the structured data too.
+                    map(opt(structured_data(options)), Option::unwrap_or_default)(input)
+                } else {
+                    structured_data_field(missing_structured_data, options)(input)
+                }
+            },
+            msg_separator(options.keep_msg_whitespace),
             rest,
         )),
         |(
             pri,
             version,
             _,
-            timestamp,
+            (raw_timestamp, timestamp),
             _,
             hostname,
-            _,
             appname,
             _,
             procid,
             _,
             msgid,
             _,
-            structured_data,
+            (structured_data, unparsed_structured_data),
             _,
             msg,
-        )| Message {
-            protocol: Protocol::RFC5424(version),
-            facility: pri.0,
-            severity: pri.1,
-            timestamp: Some(timestamp),
-            hostname,
-            appname,
-            procid: procid.map(|p| p.into()),
-            msgid,
-            structured_data,
-            msg,
+        )| {
+            let (msg, structured_data) =
+                match (options.trailing_structured_data, structured_data.is_empty()) {
+                    (true, true) => {
+                        trailing_structured_data(msg, options).unwrap_or((msg, structured_data))
+                    }
+                    _ => (msg, structured_data),
+                };
+
+            let (appname, procid) = match (options.split_appname_procid, appname, procid) {
+                (true, Some(combined), None) => match split_appname_procid(combined) {
+                    Some((appname, procid)) => (Some(appname), Some(procid)),
+                    None => (appname, procid),
+                },
+                _ => (appname, procid),
+            };
+
+            // RFC5424 allows the message to be prefixed with a UTF-8 BOM.
+            let had_bom = msg.starts_with('\u{feff}');
+            let msg = if options.keep_bom {
+                msg
+            } else {
+                msg.strip_prefix('\u{feff}').unwrap_or(msg)
+            };
+
+            Message {
+                protocol: Protocol::RFC5424(version),
+                facility: pri.0,
+                severity: pri.1,
+                timestamp: Some(timestamp),
+                raw_timestamp: Some(raw_timestamp),
+                hostname,
+                appname,
+                procid: procid.map(|p| p.into()),
+                msgid,
+                structured_data,
+                unparsed_structured_data,
+                had_bom,
+                msg,
+            }
         },
     )(input)
 }
@@ -74,14 +219,20 @@ pub(crate) fn parse(input: &str) -> IResult<&str, Message<&str>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pri::{SyslogFacility, SyslogSeverity};
+    use crate::{
+        pri::{SyslogFacility, SyslogSeverity},
+        procid::ProcId,
+    };
     use chrono::{prelude::*, Duration};
 
     #[test]
     fn parse_5424() {
         assert_eq!(
-            parse("<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - message")
-                .unwrap(),
+            parse(
+                "<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - message",
+                ParseOptions::default()
+            )
+            .unwrap(),
             (
                 "",
                 Message {
@@ -95,14 +246,267 @@ mod tests {
                             .unwrap()
                             + Duration::milliseconds(3)
                     ),
+                    raw_timestamp: Some("2003-10-11T22:14:15.003Z"),
                     hostname: Some("mymachine.example.com"),
                     appname: Some("su"),
                     procid: None,
                     msgid: Some("ID47"),
                     structured_data: vec![],
+                    unparsed_structured_data: vec![],
+                    had_bom: false,
                     msg: "message",
                 }
             )
         )
     }
+
+    #[test]
+    fn parse_5424_trailing_structured_data() {
+        let msg =
+            r#"<13>1 2003-10-11T22:14:15.003Z host app - - - the message [meta x="1"][other]"#;
+        let options = ParseOptions {
+            trailing_structured_data: true,
+            ..Default::default()
+        };
+
+        let (_, message) = parse(msg, options).unwrap();
+        assert_eq!(message.msg, "the message");
+        assert_eq!(
+            message.structured_data,
+            vec![
+                StructuredElement {
+                    id: "meta",
+                    params: vec![("x", "1")],
+                },
+                StructuredElement {
+                    id: "other",
+                    params: vec![],
+                },
+            ]
+        );
+
+        let (_, message) = parse(msg, ParseOptions::default()).unwrap();
+        assert_eq!(message.msg, r#"the message [meta x="1"][other]"#);
+        assert_eq!(message.structured_data, vec![]);
+    }
+
+    #[test]
+    fn parse_5424_trailing_structured_data_must_be_at_end() {
+        let msg = r#"<13>1 2003-10-11T22:14:15.003Z host app - - [meta x="1"] the message"#;
+        let options = ParseOptions {
+            trailing_structured_data: true,
+            ..Default::default()
+        };
+
+        let (_, message) = parse(msg, options).unwrap();
+        assert_eq!(message.msg, "the message");
+        assert_eq!(message.structured_data.len(), 1);
+
+        let msg = r#"<13>1 2003-10-11T22:14:15.003Z host app - - - the [message] continues"#;
+        let (_, message) = parse(msg, options).unwrap();
+        assert_eq!(message.msg, "the [message] continues");
+        assert_eq!(message.structured_data, vec![]);
+    }
+
+    #[test]
+    fn parse_version() {
+        assert_eq!(version("1 ").unwrap(), (" ", 1));
+        assert_eq!(version("2 ").unwrap(), (" ", 2));
+        assert_eq!(version("999 ").unwrap(), (" ", 999));
+        assert!(version("0 ").is_err());
+        assert!(version("01 ").is_err());
+
+        let (_, message) = parse(
+            "<34>2 2003-10-11T22:14:15.003Z host app - - - msg",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.protocol, Protocol::RFC5424(2));
+        assert_eq!(
+            message.to_string(),
+            "<34>2 2003-10-11T22:14:15.003+00:00 host app - - - msg"
+        );
+
+        assert!(parse(
+            "<34>0 2003-10-11T22:14:15.003Z host app - - - msg",
+            ParseOptions::default()
+        )
+        .is_err());
+        assert!(parse(
+            "<34>1000 2003-10-11T22:14:15.003Z host app - - - msg",
+            ParseOptions::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_5424_missing_hostname() {
+        let (_, message) = parse(
+            "<34>1 2003-10-11T22:14:15Z app - - msg",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, None);
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.procid, None);
+        assert_eq!(message.msgid, None);
+        assert_eq!(message.msg, "msg");
+
+        let (_, message) = parse(
+            "<34>1 2003-10-11T22:14:15Z app - - hello world",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, None);
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.procid, None);
+        assert_eq!(message.msgid, None);
+        assert_eq!(message.msg, "hello world");
+
+        // A complete header followed by broken structured data is not missing the hostname.
+        assert!(parse(
+            "<34>1 2003-10-11T22:14:15Z host app - - [bad",
+            ParseOptions::default(),
+        )
+        .is_err());
+
+        // When all the fields are present the first is the hostname.
+        let (_, message) = parse(
+            "<34>1 2003-10-11T22:14:15Z host app - - - msg",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.msg, "msg");
+    }
+
+    #[test]
+    fn parse_5424_missing_structured_data() {
+        let (_, message) = parse(
+            r#"<134>1 2020-10-30T16:05:45Z host app - - {"user":"admin"}"#,
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.procid, None);
+        assert_eq!(message.msgid, None);
+        assert_eq!(message.structured_data, vec![]);
+        assert_eq!(message.msg, r#"{"user":"admin"}"#);
+
+        let (_, message) = parse(
+            "<134>1 2020-10-30T16:05:45Z host app 123 ID47 message",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.procid, Some(ProcId::PID(123)));
+        assert_eq!(message.msgid, Some("ID47"));
+        assert_eq!(message.msg, "message");
+
+        let (_, message) = parse(
+            r#"<134>1 2020-10-30T16:05:45Z host app - - {"user": "admin", "ok": true}"#,
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.msgid, None);
+        assert_eq!(message.msg, r#"{"user": "admin", "ok": true}"#);
+
+        let (_, message) = parse(
+            "<134>1 2020-10-30T16:05:45Z host app 123 ID47 a longer message",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.msgid, Some("ID47"));
+        assert_eq!(message.msg, "a longer message");
+    }
+
+    #[test]
+    fn parse_5424_bom() {
+        let msg = "<34>1 2003-10-11T22:14:15Z host app - - - \u{feff}An application event";
+
+        let (_, message) = parse(msg, ParseOptions::default()).unwrap();
+        assert_eq!(message.msg, "An application event");
+        assert!(message.had_bom);
+
+        let options = ParseOptions {
+            keep_bom: true,
+            ..Default::default()
+        };
+        let (_, message) = parse(msg, options).unwrap();
+        assert_eq!(message.msg, "\u{feff}An application event");
+        assert!(message.had_bom);
+
+        let (_, message) = parse(
+            "<34>1 2003-10-11T22:14:15Z host app - - - An application event",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.msg, "An application event");
+        assert!(!message.had_bom);
+    }
+
+    #[test]
+    fn parse_5424_require_pri() {
+        let msg = "1 2003-10-11T22:14:15.003Z host app - - - message";
+        let options = ParseOptions {
+            require_pri: true,
+            ..Default::default()
+        };
+
+        assert!(parse(msg, ParseOptions::default()).is_ok());
+        assert!(parse(msg, options).is_err());
+        assert!(parse(&format!("<34>{}", msg), options).is_ok());
+    }
+
+    #[test]
+    fn parse_5424_split_appname_procid() {
+        let msg = "<34>1 2003-10-11T22:14:15Z host app:123 - - - msg";
+        let options = ParseOptions {
+            split_appname_procid: true,
+            ..Default::default()
+        };
+
+        let (_, message) = parse(msg, options).unwrap();
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.procid, Some(ProcId::PID(123)));
+
+        let (_, message) = parse(msg, ParseOptions::default()).unwrap();
+        assert_eq!(message.appname, Some("app:123"));
+        assert_eq!(message.procid, None);
+
+        // Only digits following the colon are split off.
+        let (_, message) = parse(
+            "<28>1 2020-05-22T14:59:09.250-03:00 host OX-XXX-CONTEUDO:rpd 6589 - - msg",
+            options,
+        )
+        .unwrap();
+        assert_eq!(message.appname, Some("OX-XXX-CONTEUDO:rpd"));
+        assert_eq!(message.procid, Some(ProcId::PID(6589)));
+
+        // An existing procid is left alone.
+        let (_, message) =
+            parse("<34>1 2003-10-11T22:14:15Z host app:1 2 - - msg", options).unwrap();
+        assert_eq!(message.appname, Some("app:1"));
+        assert_eq!(message.procid, Some(ProcId::PID(2)));
+    }
+
+    #[test]
+    fn parse_5424_spaces_after_version() {
+        let (_, message) = parse(
+            "<34>1   2003-10-11T22:14:15.003Z host app - - - msg",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(message.protocol, Protocol::RFC5424(1));
+        assert_eq!(message.raw_timestamp, Some("2003-10-11T22:14:15.003Z"));
+        assert_eq!(message.hostname, Some("host"));
+        assert_eq!(message.appname, Some("app"));
+        assert_eq!(message.msg, "msg");
+    }
 }
@@ -1,15 +1,17 @@
+use crate::options::ParseOptions;
 use nom::{
     branch::alt,
     bytes::complete::{escaped, tag, take_till1, take_until, take_while1},
-    character::complete::{anychar, space0},
-    combinator::map,
-    multi::{many1, separated_list0},
+    character::complete::{anychar, space0, space1},
+    combinator::{consumed, eof, map, not, opt, peek},
+    multi::{many_m_n, separated_list0},
     sequence::{delimited, separated_pair, terminated, tuple},
     IResult,
 };
 use std::fmt;
 
 #[derive(Clone, Debug, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructuredElement<S: AsRef<str> + Ord + Clone> {
     pub id: S,
     pub params: Vec<(S, S)>,
@@ -32,6 +34,177 @@ impl<S: AsRef<str> + Ord + Clone> StructuredElement<S> {
             params: &self.params,
         }
     }
+
+    /// Returns the value of the first param with the given name, with the escapes stripped.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.params
+            .iter()
+            .find(|(name, _)| name.as_ref() == key)
+            .map(|(_, value)| unescape(value.as_ref()))
+    }
+
+    /// Returns the name part of the id, before any `@`. eg. `exampleSDID` for `exampleSDID@32473`.
+    /// Some senders leave out the name entirely, eg. `@32473`, in which case this is empty.
+    pub fn name(&self) -> &str {
+        let id = self.id.as_ref();
+        id.split_once('@').map_or(id, |(name, _)| name)
+    }
+
+    /// Returns the private enterprise number following the `@` in the id,
+    /// eg. `32473` for both `exampleSDID@32473` and `exampleSDID@32473.1.2`.
+    /// Returns `None` for the IANA registered ids, such as `timeQuality`, that have no `@`.
+    pub fn enterprise_id(&self) -> Option<u32> {
+        let (_, enterprise) = self.id.as_ref().split_once('@')?;
+        enterprise.split('.').next()?.parse().ok()
+    }
+
+    /// Returns a builder for an element with the given id.
+    pub fn builder(id: S) -> StructuredElementBuilder<S> {
+        StructuredElementBuilder {
+            element: StructuredElement { id, params: vec![] },
+        }
+    }
+}
+
+/// A typed view of the `timeQuality` structured data element defined by RFC5424.
+/// Any param that is missing or has an invalid value is `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeQuality {
+    /// `tzKnown` - whether the sender knows its timezone.
+    pub tz_known: Option<bool>,
+    /// `isSynced` - whether the sender's clock is synchronized to a reliable source.
+    pub is_synced: Option<bool>,
+    /// `syncAccuracy` - how accurate the sender believes its clock to be, in microseconds.
+    pub sync_accuracy: Option<u64>,
+}
+
+impl TimeQuality {
+    pub(crate) fn from_element<S: AsRef<str> + Ord + Clone>(
+        element: &StructuredElement<S>,
+    ) -> Self {
+        let flag = |name| match element.get(name)?.as_str() {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        };
+
+        TimeQuality {
+            tz_known: flag("tzKnown"),
+            is_synced: flag("isSynced"),
+            sync_accuracy: element
+                .get("syncAccuracy")
+                .and_then(|value| value.parse().ok()),
+        }
+    }
+}
+
+/// A typed view of the `origin` structured data element defined by RFC5424.
+/// Any param that is missing is `None`. If a param is repeated, eg. a sender with several
+/// ip addresses, the first is used.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Origin {
+    /// `ip` - the ip address of the sender.
+    pub ip: Option<String>,
+    /// `enterpriseId` - the private enterprise number of the software, eg. `32473` or `32473.1.2`.
+    pub enterprise_id: Option<String>,
+    /// `software` - the name of the software that generated the message.
+    pub software: Option<String>,
+    /// `swVersion` - the version of the software that generated the message.
+    pub sw_version: Option<String>,
+}
+
+impl Origin {
+    pub(crate) fn from_element<S: AsRef<str> + Ord + Clone>(
+        element: &StructuredElement<S>,
+    ) -> Self {
+        Origin {
+            ip: element.get("ip"),
+            enterprise_id: element.get("enterpriseId"),
+            software: element.get("software"),
+            sw_version: element.get("swVersion"),
+        }
+    }
+}
+
+/// Returns true if the name only contains characters that are valid in an SD-NAME.
+/// That is 1 to 32 printable ASCII characters, excluding `=`, space, `]` and `"`.
+fn is_sd_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .bytes()
+            .all(|c| c.is_ascii_graphic() && c != b'=' && c != b']' && c != b'"')
+}
+
+/// The SD-IDs registered with IANA. These are the only ids that may be written without an `@`.
+const IANA_SD_IDS: &[&str] = &["timeQuality", "origin", "meta"];
+
+/// Returns true if the id is a valid SD-ID. It must either be one of the IANA registered ids,
+/// or be of the form `name@number`, where the number is a private enterprise number such as
+/// `32473` or `32473.1.2`.
+fn is_sd_id(id: &str) -> bool {
+    is_sd_name(id)
+        && match id.split_once('@') {
+            None => IANA_SD_IDS.contains(&id),
+            Some((name, enterprise)) => {
+                !name.is_empty()
+                    && enterprise
+                        .split('.')
+                        .all(|part| !part.is_empty() && part.bytes().all(|c| c.is_ascii_digit()))
+            }
+        }
+}
+
+/// Returns true if the element's id and param names are valid as per RFC5424.
+fn is_conformant(element: &StructuredElement<&str>) -> bool {
+    is_sd_id(element.id) && element.params.iter().all(|(name, _)| is_sd_name(name))
+}
+
+/// Builds a `StructuredElement` a param at a time.
+#[derive(Clone, Debug)]
+pub struct StructuredElementBuilder<S: AsRef<str> + Ord + Clone> {
+    element: StructuredElement<S>,
+}
+
+impl<S: AsRef<str> + Ord + Clone> StructuredElementBuilder<S> {
+    /// Add a param. The value should already be escaped, see `escape_param_value`.
+    pub fn with_param(mut self, name: S, value: S) -> Self {
+        self.element.params.push((name, value));
+        self
+    }
+
+    /// Add a param, checking that the name only contains characters that are valid in an
+    /// SD-NAME so that the element can be serialized.
+    pub fn try_with_param(self, name: S, value: S) -> Result<Self, String> {
+        if is_sd_name(name.as_ref()) {
+            Ok(self.with_param(name, value))
+        } else {
+            Err(format!("invalid param name: {:?}", name.as_ref()))
+        }
+    }
+
+    /// Returns the built element.
+    pub fn build(self) -> StructuredElement<S> {
+        self.element
+    }
+}
+
+impl<S: AsRef<str> + Ord + Clone> StructuredElement<S> {
+    /// Convert the id and params to another string type.
+    pub(crate) fn map_str<T, F>(self, f: F) -> StructuredElement<T>
+    where
+        T: AsRef<str> + Ord + Clone,
+        F: Fn(S) -> T,
+    {
+        StructuredElement {
+            id: f(self.id),
+            params: self
+                .params
+                .into_iter()
+                .map(|(name, value)| (f(name), f(value)))
+                .collect(),
+        }
+    }
 }
 
 impl<S: AsRef<str> + Ord + Clone> fmt::Display for StructuredElement<S> {
@@ -80,6 +253,33 @@ impl From<StructuredElement<&str>> for StructuredElement<String> {
     }
 }
 
+/// Strip the escapes from a param value.
+fn unescape(value: &str) -> String {
+    let mut trimmed = String::with_capacity(value.len());
+    let mut escaped = false;
+    for c in value.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else if c == 'n' && escaped {
+            escaped = false;
+            trimmed.push('\n');
+        } else if c != '"' && c != ']' && c != '\\' && escaped {
+            // If the character following the escape isn't a \, " or ] we treat it like an normal unescaped character.
+            escaped = false;
+            trimmed.push('\\');
+            trimmed.push(c);
+        } else {
+            escaped = false;
+            trimmed.push(c);
+        }
+    }
+    if escaped {
+        // A trailing backslash has nothing to escape, so keep it.
+        trimmed.push('\\');
+    }
+    trimmed
+}
+
 impl<'a, S: AsRef<str> + Ord + Clone> Iterator for ParamsIter<'a, S> {
     type Item = (&'a S, String);
 
@@ -89,30 +289,27 @@ impl<'a, S: AsRef<str> + Ord + Clone> Iterator for ParamsIter<'a, S> {
         } else {
             let (key, value) = &self.params[self.pos];
             self.pos += 1;
-            let mut trimmed = String::with_capacity(value.as_ref().len());
-            let mut escaped = false;
-            for c in value.as_ref().chars() {
-                if c == '\\' && !escaped {
-                    escaped = true;
-                } else if c == 'n' && escaped {
-                    escaped = false;
-                    trimmed.push('\n');
-                } else if c != '"' && c != ']' && c != '\\' && escaped {
-                    // If the character following the escape isn't a \, " or ] we treat it like an normal unescaped character.
-                    escaped = false;
-                    trimmed.push('\\');
-                    trimmed.push(c);
-                } else {
-                    escaped = false;
-                    trimmed.push(c);
-                }
-            }
-            Some((key, trimmed))
+            Some((key, unescape(value.as_ref())))
         }
     }
 }
 
+/// Escape a param value so it can be written inside the quotes of a structured data element.
+/// `\`, `"` and `]` are escaped with a `\`. This is the inverse of the unescaping done by
+/// `StructuredElement::params`.
+pub fn escape_param_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || c == '"' || c == ']' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 /// Parse the param value - a string delimited by '"' - '\' escapes \ and "
+/// Any other characters, including control characters such as newlines, are taken as is.
 fn param_value(input: &str) -> IResult<&str, &str> {
     alt((
         // We need to handle an empty string separately since `escaped`
@@ -126,67 +323,215 @@ fn param_value(input: &str) -> IResult<&str, &str> {
     ))(input)
 }
 
+/// Parse a param value delimited by `'` - `\` escapes `\` and `'`.
+/// Some non-compliant senders quote values this way, eg. `[x y='hello world']`.
+fn param_value_single_quoted(input: &str) -> IResult<&str, &str> {
+    alt((
+        map(tag("''"), |_| ""),
+        delimited(
+            tag("'"),
+            escaped(take_while1(|c: char| c != '\\' && c != '\''), '\\', anychar),
+            tag("'"),
+        ),
+    ))(input)
+}
+
+/// Returns the parser for param values, also accepting single quoted values if `single_quotes` is set.
+fn any_param_value(single_quotes: bool) -> fn(&str) -> IResult<&str, &str> {
+    if single_quotes {
+        |input| alt((param_value, param_value_single_quoted))(input)
+    } else {
+        param_value
+    }
+}
+
 /// Parse a param name="value"
-fn param(input: &str) -> IResult<&str, (&str, &str)> {
-    separated_pair(
-        take_till1(|c: char| c == ']' || c == '='),
-        terminated(tag("="), space0),
-        param_value,
-    )(input)
+fn param(single_quotes: bool) -> impl FnMut(&str) -> IResult<&str, (&str, &str)> {
+    move |input| {
+        separated_pair(
+            take_till1(|c: char| c == ']' || c == '='),
+            terminated(tag("="), space0),
+            any_param_value(single_quotes),
+        )(input)
+    }
 }
 
 /// Parse a single structured data record.
 /// [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]
-fn structured_datum_strict(input: &str) -> IResult<&str, Option<StructuredElement<&str>>> {
-    delimited(
-        tag("["),
-        map(
-            tuple((
-                take_till1(|c: char| c.is_whitespace() || c == ']' || c == '='),
-                space0,
-                separated_list0(tag(" "), param),
-            )),
-            |(id, _, params)| Some(StructuredElement { id, params }),
-        ),
-        tag("]"),
-    )(input)
+fn structured_datum_strict(
+    single_quotes: bool,
+) -> impl FnMut(&str) -> IResult<&str, Option<StructuredElement<&str>>> {
+    move |input| {
+        delimited(
+            tag("["),
+            map(
+                tuple((
+                    take_till1(|c: char| c.is_whitespace() || c == ']' || c == '='),
+                    space0,
+                    separated_list0(tag(" "), param(single_quotes)),
+                )),
+                |(id, _, params)| Some(StructuredElement { id, params }),
+            ),
+            tag("]"),
+        )(input)
+    }
+}
+
+/// Parse a param, dropping it if it has no name, eg. `="value"`.
+fn param_allow_unnamed(
+    single_quotes: bool,
+) -> impl FnMut(&str) -> IResult<&str, Option<(&str, &str)>> {
+    move |input| {
+        alt((
+            map(param(single_quotes), Some),
+            map(
+                tuple((tag("="), space0, any_param_value(single_quotes))),
+                |_| None,
+            ),
+        ))(input)
+    }
+}
+
+/// Parse a single structured data record, dropping any params that have no name.
+/// [exampleSDID@32473 ="3" eventSource="Application"]
+fn structured_datum_unnamed_params(
+    single_quotes: bool,
+) -> impl FnMut(&str) -> IResult<&str, Option<StructuredElement<&str>>> {
+    move |input| {
+        delimited(
+            tag("["),
+            map(
+                tuple((
+                    take_till1(|c: char| c.is_whitespace() || c == ']' || c == '='),
+                    space0,
+                    separated_list0(tag(" "), param_allow_unnamed(single_quotes)),
+                )),
+                |(id, _, params)| {
+                    Some(StructuredElement {
+                        id,
+                        params: params.into_iter().flatten().collect(),
+                    })
+                },
+            ),
+            tag("]"),
+        )(input)
+    }
 }
 
 /// Parse a single structured data record allowing anything between brackets.
-fn structured_datum_permissive(input: &str) -> IResult<&str, Option<StructuredElement<&str>>> {
-    alt((
-        structured_datum_strict,
-        // If the element fails to parse, just parse it and return None.
-        delimited(tag("["), map(take_until("]"), |_| None), tag("]")),
-    ))(input)
+fn structured_datum_permissive(
+    single_quotes: bool,
+) -> impl FnMut(&str) -> IResult<&str, Option<StructuredElement<&str>>> {
+    move |input| {
+        alt((
+            structured_datum_strict(single_quotes),
+            structured_datum_unnamed_params(single_quotes),
+            // If the element fails to parse, just parse it and return None.
+            delimited(tag("["), map(take_until("]"), |_| None), tag("]")),
+        ))(input)
+    }
 }
 
 /// Parse a single structured data record.
+/// If the `strict_sd_ids` option is set, an element with an invalid id or param name is
+/// treated as failing to parse.
 fn structured_datum(
     allow_failure: bool,
+    options: ParseOptions,
 ) -> impl FnMut(&str) -> IResult<&str, Option<StructuredElement<&str>>> {
-    if allow_failure {
-        structured_datum_permissive
-    } else {
-        structured_datum_strict
+    move |input| {
+        let single_quotes = options.single_quoted_sd_values;
+        let (rest, element) = if allow_failure {
+            structured_datum_permissive(single_quotes)(input)?
+        } else {
+            structured_datum_strict(single_quotes)(input)?
+        };
+
+        match element {
+            Some(element) if options.strict_sd_ids && !is_conformant(&element) => {
+                if allow_failure {
+                    Ok((rest, None))
+                } else {
+                    Err(nom::Err::Error(nom::error::Error::new(
+                        input,
+                        nom::error::ErrorKind::Verify,
+                    )))
+                }
+            }
+            element => Ok((rest, element)),
+        }
     }
 }
 
-/// Parse multiple structured data elements.
-pub(crate) fn structured_data(input: &str) -> IResult<&str, Vec<StructuredElement<&str>>> {
-    structured_data_optional(true)(input)
+/// The parsed structured data elements along with the raw text of any elements that
+/// couldn't be parsed.
+pub(crate) type StructuredDataWithUnparsed<'a> = (Vec<StructuredElement<&'a str>>, Vec<&'a str>);
+
+/// Parse multiple structured data elements, keeping the raw text of any elements that fail to
+/// parse so they are not silently lost.
+/// Parsing stops after `options.max_sd_elements` elements, leaving the rest unparsed.
+/// A `-` is always taken as the NILVALUE, meaning there is no structured data.
+pub(crate) fn structured_data(
+    options: ParseOptions,
+) -> impl FnMut(&str) -> IResult<&str, StructuredDataWithUnparsed> {
+    elements(true, |input| tag("-")(input), options)
+}
+
+/// The NILVALUE where the structured data is optional. Since a `-` could be the start of
+/// the message, it is only taken as the NILVALUE if it is followed by whitespace and more text.
+fn optional_nil_value(input: &str) -> IResult<&str, &str> {
+    terminated(tag("-"), peek(tuple((space1, not(eof)))))(input)
 }
 
 /// Parse multiple structured data elements.
+/// A `-` is only taken as the NILVALUE if it is followed by whitespace and more text, so a
+/// message that is, or starts with, a `-` is left alone.
+/// Parsing stops after `options.max_sd_elements` elements, leaving the rest unparsed.
 pub(crate) fn structured_data_optional(
     allow_failure: bool,
+    options: ParseOptions,
 ) -> impl FnMut(&str) -> IResult<&str, Vec<StructuredElement<&str>>> {
     move |input| {
+        map(
+            elements(allow_failure, optional_nil_value, options),
+            |(elements, _)| elements,
+        )(input)
+    }
+}
+
+/// Parse multiple structured data elements, splitting them into the elements that parsed
+/// and the raw text of those that didn't.
+fn elements(
+    allow_failure: bool,
+    nil_value: fn(&str) -> IResult<&str, &str>,
+    options: ParseOptions,
+) -> impl FnMut(&str) -> IResult<&str, StructuredDataWithUnparsed> {
+    move |input| {
+        if options.max_sd_elements == Some(0) {
+            // No elements are to be parsed, so any structured data is left as part of the message.
+            return map(opt(nil_value), |_| (vec![], vec![]))(input);
+        }
+
         alt((
-            map(tag("-"), |_| vec![]),
-            map(many1(structured_datum(allow_failure)), |items| {
-                items.iter().filter_map(|item| item.clone()).collect()
-            }),
+            map(nil_value, |_| (vec![], vec![])),
+            map(
+                many_m_n(
+                    1,
+                    options.max_sd_elements.unwrap_or(usize::MAX),
+                    consumed(structured_datum(allow_failure, options)),
+                ),
+                |items| {
+                    let mut elements = Vec::with_capacity(items.len());
+                    let mut unparsed = Vec::new();
+                    for (raw, item) in items {
+                        match item {
+                            Some(element) => elements.push(element),
+                            None => unparsed.push(raw),
+                        }
+                    }
+                    (elements, unparsed)
+                },
+            ),
         ))(input)
     }
 }
@@ -203,6 +548,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_param_value_control_characters() {
+        assert_eq!(
+            param_value("\"line1\nline2\tcolumn\"").unwrap(),
+            ("", "line1\nline2\tcolumn")
+        );
+        assert_eq!(
+            structured_datum_strict(false)("[meta detail=\"line1\nline2\"]").unwrap(),
+            (
+                "",
+                Some(StructuredElement {
+                    id: "meta",
+                    params: vec![("detail", "line1\nline2")]
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn parse_single_quoted_param_value() {
+        assert_eq!(
+            structured_datum_strict(true)("[x y='hello world']").unwrap(),
+            (
+                "",
+                Some(StructuredElement {
+                    id: "x",
+                    params: vec![("y", "hello world")]
+                })
+            )
+        );
+        assert_eq!(
+            param_value_single_quoted(r"'it\'s'").unwrap(),
+            ("", r"it\'s")
+        );
+        assert_eq!(param_value_single_quoted("''").unwrap(), ("", ""));
+
+        assert!(structured_datum_strict(false)("[x y='hello world']").is_err());
+    }
+
+    #[test]
+    fn parse_mixed_quoted_param_values() {
+        let (_, element) = structured_datum_strict(true)(r#"[x a="1" b='2']"#).unwrap();
+        let element = element.unwrap();
+        assert_eq!(element.params, vec![("a", "1"), ("b", "2")]);
+
+        // Only the RFC5424 escapes are stripped when reading the value.
+        let (_, element) =
+            structured_datum_strict(true)(r#"[meta id='a\'b\]c\\d' q="it's"]"#).unwrap();
+        let element = element.unwrap();
+        assert_eq!(element.get("id"), Some(r"a\'b]c\d".to_string()));
+        assert_eq!(element.get("q"), Some("it's".to_string()));
+
+        assert!(structured_datum_strict(false)(r#"[x a="1" b='2']"#).is_err());
+        assert_eq!(
+            structured_datum(true, ParseOptions::default())(r#"[x a="1" b='2']"#),
+            Ok(("", None))
+        );
+    }
+
     #[test]
     fn parse_empty_param_value() {
         assert_eq!(param_value(r#""""#).unwrap(), ("", ""));
@@ -211,7 +615,7 @@ mod tests {
     #[test]
     fn parse_structured_data() {
         assert_eq!(
-            structured_datum_strict(
+            structured_datum_strict(false)(
                 "[exampleSDID@32473 iut=\"3\" eventSource=\"Application\" eventID=\"1011\"]"
             )
             .unwrap(),
@@ -229,10 +633,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_structured_data_enterprise_id() {
+        let (_, element) = structured_datum_strict(false)(r#"[exampleSDID@32473 k="v"]"#).unwrap();
+        let element = element.unwrap();
+        assert_eq!(element.name(), "exampleSDID");
+        assert_eq!(element.enterprise_id(), Some(32473));
+
+        let (_, element) = structured_datum_strict(false)(r#"[@32473 k="v"]"#).unwrap();
+        let element = element.unwrap();
+        assert_eq!(element.id, "@32473");
+        assert_eq!(element.name(), "");
+        assert_eq!(element.enterprise_id(), Some(32473));
+        assert_eq!(element.params, vec![("k", "v")]);
+
+        let (_, element) = structured_datum_strict(false)(r#"[timeQuality tzKnown="1"]"#).unwrap();
+        let element = element.unwrap();
+        assert_eq!(element.name(), "timeQuality");
+        assert_eq!(element.enterprise_id(), None);
+    }
+
     #[test]
     fn parse_structured_data_no_values() {
         assert_eq!(
-            structured_datum(false)("[exampleSDID@32473]").unwrap(),
+            structured_datum(false, ParseOptions::default())("[exampleSDID@32473]").unwrap(),
             (
                 "",
                 Some(StructuredElement {
@@ -246,7 +670,7 @@ mod tests {
     #[test]
     fn parse_structured_data_with_space() {
         assert_eq!(
-            structured_datum(false)(
+            structured_datum(false, ParseOptions::default())(
                 "[exampleSDID@32473 iut=\"3\" eventSource= \"Application\" eventID=\"1011\"]"
             )
             .unwrap(),
@@ -267,20 +691,86 @@ mod tests {
     #[test]
     fn parse_invalid_structured_data() {
         assert_eq!(
-            structured_datum(true)("[exampleSDID@32473 iut=]"),
+            structured_datum(true, ParseOptions::default())("[exampleSDID@32473 iut=]"),
             Ok(("", None))
         );
     }
 
+    #[test]
+    fn parse_structured_data_unnamed_param() {
+        assert_eq!(
+            structured_datum(true, ParseOptions::default())(r#"[x ="v" y="ok"]"#),
+            Ok((
+                "",
+                Some(StructuredElement {
+                    id: "x",
+                    params: vec![("y", "ok")],
+                })
+            ))
+        );
+
+        assert!(structured_datum(false, ParseOptions::default())(r#"[x ="v" y="ok"]"#).is_err());
+    }
+
+    #[test]
+    fn parse_structured_data_strict_ids() {
+        let options = ParseOptions {
+            strict_sd_ids: true,
+            ..Default::default()
+        };
+
+        for valid in [
+            r#"[exampleSDID@32473 iut="3"]"#,
+            r#"[exampleSDID@32473.1.2 iut="3"]"#,
+            r#"[timeQuality tzKnown="1"]"#,
+        ]
+        .iter()
+        {
+            assert!(
+                matches!(structured_datum(false, options)(valid), Ok(("", Some(_)))),
+                "{}",
+                valid
+            );
+        }
+
+        for invalid in [
+            r#"[exampleSDID@32473@1 iut="3"]"#,
+            r#"[exampleSDID iut="3"]"#,
+            r#"[@32473 iut="3"]"#,
+            r#"[exampleSDID@ iut="3"]"#,
+            r#"[exampleSDID@abc iut="3"]"#,
+            r#"[exampleSDID@32473 "iut"="3"]"#,
+        ]
+        .iter()
+        {
+            assert!(
+                structured_datum(false, options)(invalid).is_err(),
+                "{}",
+                invalid
+            );
+            assert_eq!(
+                structured_datum(true, options)(invalid),
+                Ok(("", None)),
+                "{}",
+                invalid
+            );
+            assert!(
+                structured_datum(false, ParseOptions::default())(invalid).is_ok(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
     #[test]
     fn parse_multiple_structured_data() {
         assert_eq!(
-            structured_data(
+            structured_data(ParseOptions::default())(
                 "[exampleSDID@32473 iut=\"3\" eventSource= \"Application\" eventID=\"1011\"][sproink onk=\"ponk\" zork=\"shnork\"]"
             ) .unwrap(),
             (
                 "",
-                vec![
+                (vec![
                     StructuredElement {
                         id: "exampleSDID@32473",
                         params: vec![
@@ -296,15 +786,35 @@ mod tests {
                             ("zork", "shnork"),
                         ]
                     }
-                ]
+                ], vec![])
             )
         );
     }
 
+    #[test]
+    fn parse_structured_data_nil_value() {
+        assert_eq!(
+            structured_data(ParseOptions::default())("-").unwrap(),
+            ("", (vec![], vec![]))
+        );
+        assert_eq!(
+            structured_data(ParseOptions::default())("- msg").unwrap(),
+            (" msg", (vec![], vec![]))
+        );
+
+        assert!(structured_data_optional(false, ParseOptions::default())("-").is_err());
+        assert!(structured_data_optional(false, ParseOptions::default())("-msg").is_err());
+        assert!(structured_data_optional(false, ParseOptions::default())("- ").is_err());
+        assert_eq!(
+            structured_data_optional(false, ParseOptions::default())("- msg").unwrap(),
+            (" msg", vec![])
+        );
+    }
+
     #[test]
     fn parse_structured_data_keep_invalid_elements() {
         assert_eq!(
-            structured_data_optional(false)("[abc][id aa=]").unwrap(),
+            structured_data_optional(false, ParseOptions::default())("[abc][id aa=]").unwrap(),
             (
                 "[id aa=]",
                 vec![StructuredElement {
@@ -316,46 +826,101 @@ mod tests {
     }
 
     #[test]
-    fn parse_structured_data_ignores_invalid_elements() {
+    fn parse_structured_data_keeps_raw_invalid_elements() {
         assert_eq!(
-            structured_data("[abc][id aa=]").unwrap(),
+            structured_data(ParseOptions::default())("[abc][id aa=]").unwrap(),
             (
                 "",
-                vec![StructuredElement {
-                    id: "abc",
-                    params: vec![],
-                },]
+                (
+                    vec![StructuredElement {
+                        id: "abc",
+                        params: vec![],
+                    },],
+                    vec!["[id aa=]"]
+                )
             )
         )
     }
 
+    #[test]
+    fn parse_structured_data_max_elements() {
+        let options = ParseOptions {
+            max_sd_elements: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            structured_data(options)("[a][b x=\"1\"][c][d][e] msg").unwrap(),
+            (
+                "[c][d][e] msg",
+                (
+                    vec![
+                        StructuredElement {
+                            id: "a",
+                            params: vec![],
+                        },
+                        StructuredElement {
+                            id: "b",
+                            params: vec![("x", "1")],
+                        },
+                    ],
+                    vec![]
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn parse_structured_data_no_elements() {
+        let options = ParseOptions {
+            max_sd_elements: Some(0),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            structured_data(options)("[a][b x=\"1\"] msg").unwrap(),
+            ("[a][b x=\"1\"] msg", (vec![], vec![]))
+        );
+        assert_eq!(
+            structured_data(options)("- msg").unwrap(),
+            (" msg", (vec![], vec![]))
+        );
+        assert_eq!(
+            structured_data_optional(true, options)("[a] msg").unwrap(),
+            ("[a] msg", vec![])
+        );
+    }
+
     #[test]
     fn parse_multiple_structured_data_first_item_id_only() {
         assert_eq!(
-            structured_data("[abc][id aa=\"bb\"]").unwrap(),
+            structured_data(ParseOptions::default())("[abc][id aa=\"bb\"]").unwrap(),
             (
                 "",
-                vec![
-                    StructuredElement {
-                        id: "abc",
-                        params: vec![],
-                    },
-                    StructuredElement {
-                        id: "id",
-                        params: vec![("aa", "bb")],
-                    },
-                ]
+                (
+                    vec![
+                        StructuredElement {
+                            id: "abc",
+                            params: vec![],
+                        },
+                        StructuredElement {
+                            id: "id",
+                            params: vec![("aa", "bb")],
+                        },
+                    ],
+                    vec![]
+                )
             )
         )
     }
 
     #[test]
     fn params_remove_escapes() {
-        let data = structured_data(
+        let (_, (data, _)) = structured_data(ParseOptions::default())(
             r#"[id aa="hullo \"there\"" bb="let's \\\\do this\\\\" cc="hello [bye\]" dd="hello\nbye" ee="not \esc\aped"]"#,
         )
         .unwrap();
-        let params = data.1[0].params().collect::<Vec<_>>();
+        let params = data[0].params().collect::<Vec<_>>();
 
         assert_eq!(
             params,
@@ -374,6 +939,81 @@ bye"#
         );
     }
 
+    #[test]
+    fn params_keep_escaped_single_quote() {
+        let (_, (data, _)) = structured_data(ParseOptions::default())(r#"[x y="it\'s"]"#).unwrap();
+
+        assert_eq!(data[0].get("y"), Some(r"it\'s".to_string()));
+        assert_eq!(
+            data[0].params().collect::<Vec<_>>(),
+            vec![(&"y", r"it\'s".to_string())]
+        );
+    }
+
+    #[test]
+    fn params_keep_trailing_backslash() {
+        let element = StructuredElement {
+            id: "id",
+            params: vec![("aa", r#"trailing\"#)],
+        };
+
+        assert_eq!(
+            element.params().collect::<Vec<_>>(),
+            vec![(&"aa", r#"trailing\"#.to_string())]
+        );
+    }
+
+    #[test]
+    fn get_param() {
+        let element = StructuredElement {
+            id: "id",
+            params: vec![
+                ("aa", r#"hullo \"there\""#),
+                ("bb", "first"),
+                ("bb", "second"),
+            ],
+        };
+
+        assert_eq!(element.get("aa"), Some(r#"hullo "there""#.to_string()));
+        assert_eq!(element.get("bb"), Some("first".to_string()));
+        assert_eq!(element.get("cc"), None);
+    }
+
+    #[test]
+    fn builder() {
+        let element = StructuredElement::builder("meta")
+            .with_param("sequenceId", "1")
+            .try_with_param("language", "EN")
+            .unwrap()
+            .build();
+
+        assert_eq!(
+            element,
+            StructuredElement {
+                id: "meta",
+                params: vec![("sequenceId", "1"), ("language", "EN")],
+            }
+        );
+
+        assert!(StructuredElement::builder("meta")
+            .try_with_param("sys up time", "37")
+            .is_err());
+        assert!(StructuredElement::builder("meta")
+            .try_with_param("a=b", "37")
+            .is_err());
+        assert!(StructuredElement::builder("meta")
+            .try_with_param("", "37")
+            .is_err());
+    }
+
+    #[test]
+    fn escape_values() {
+        assert_eq!(
+            escape_param_value(r#"say "hi" [there] \o/"#),
+            r#"say \"hi\" [there\] \\o/"#
+        );
+    }
+
     #[test]
     fn sd_param_escapes() {
         let (_, value) = param_value(r#""Here are some escaped characters -> \"\\\]""#).unwrap();
@@ -1,19 +1,27 @@
-use crate::parsers::digits;
+use crate::{options::ParseOptions, parsers::digits};
 use chrono::prelude::*;
 use nom::{
     branch::alt,
     bytes::complete::take_until,
-    bytes::complete::{tag, take},
-    character::complete::space1,
-    combinator::{map, map_res, opt},
+    bytes::complete::{tag, take, take_while_m_n},
+    character::complete::{digit1, one_of, space0, space1},
+    combinator::{cond, eof, map, map_opt, map_res, opt, peek, verify},
     error::{self, ErrorKind},
-    sequence::tuple,
+    sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
 
 /// The timestamp for 5424 messages yyyy-mm-ddThh:mm:ss.mmmmZ
+/// Fractional seconds with more than 9 digits are truncated to nanosecond precision.
+/// Some locales separate the fractional seconds with a ',', which is also accepted.
 pub(crate) fn timestamp_3339(input: &str) -> IResult<&str, DateTime<FixedOffset>> {
-    map_res(take_until(" "), chrono::DateTime::parse_from_rfc3339)(input)
+    map_res(take_until(" "), |timestamp: &str| {
+        if timestamp.contains(',') {
+            DateTime::parse_from_rfc3339(&timestamp.replacen(',', ".", 1))
+        } else {
+            DateTime::parse_from_rfc3339(timestamp)
+        }
+    })(input)
 }
 
 /// An incomplete date is a tuple of (month, date, hour, minutes, seconds)
@@ -38,25 +46,100 @@ fn parse_month(s: &str) -> Result<u32, String> {
     }
 }
 
-/// The timestamp for 3164 messages. MMM DD HH:MM:SS
-fn timestamp_3164_no_year(input: &str) -> IResult<&str, IncompleteDate> {
-    map(
-        tuple((
-            map_res(take(3_usize), parse_month),
-            space1,
-            digits,
-            space1,
-            digits,
-            tag(":"),
-            digits,
-            tag(":"),
-            digits,
-            opt(tag(":")),
-        )),
-        |(month, _, date, _, hour, _, minute, _, seconds, _)| (month, date, hour, minute, seconds),
+/// The month as a two digit number, 01 to 12.
+fn numeric_month(input: &str) -> IResult<&str, u32> {
+    verify(
+        map_res(
+            take_while_m_n(2, 2, |c: char| c.is_ascii_digit()),
+            str::parse,
+        ),
+        |month| (1..=12).contains(month),
     )(input)
 }
 
+/// Returns the parser for the month. If `numeric` is set, a two digit month such as `01`
+/// is accepted as well as the month name.
+fn month(numeric: bool) -> fn(&str) -> IResult<&str, u32> {
+    if numeric {
+        |input| alt((map_res(take(3_usize), parse_month), numeric_month))(input)
+    } else {
+        |input| map_res(take(3_usize), parse_month)(input)
+    }
+}
+
+/// Fractional seconds, separated from the seconds by either a '.' or a ','.
+/// Returns the number of nanoseconds, any digits beyond nanosecond precision are truncated.
+pub(crate) fn fraction(input: &str) -> IResult<&str, u32> {
+    map(preceded(one_of(".,"), digit1), |fraction: &str| {
+        let fraction = &fraction[..fraction.len().min(9)];
+        fraction
+            .bytes()
+            .chain(std::iter::repeat(b'0'))
+            .take(9)
+            .fold(0, |nanos, digit| nanos * 10 + (digit - b'0') as u32)
+    })(input)
+}
+
+/// The timestamp for 3164 messages. MMM DD HH:MM:SS
+/// The seconds can optionally be followed by fractional seconds, MMM DD HH:MM:SS,mmm
+/// If `numeric_month` is set the month can also be two digits, MM DD HH:MM:SS.
+/// Returns the date along with the nanoseconds.
+fn timestamp_3164_no_year(
+    numeric_month: bool,
+) -> impl Fn(&str) -> IResult<&str, (IncompleteDate, u32)> {
+    move |input| {
+        map(
+            tuple((
+                month(numeric_month),
+                space1,
+                digits,
+                space1,
+                digits,
+                tag(":"),
+                digits,
+                tag(":"),
+                digits,
+                opt(fraction),
+                opt(tag(":")),
+            )),
+            |(month, _, date, _, hour, _, minute, _, seconds, nanos, _)| {
+                ((month, date, hour, minute, seconds), nanos.unwrap_or(0))
+            },
+        )(input)
+    }
+}
+
+/// Timestamp with the year following the time, as sent by some devices. MMM DD HH:MM:SS YYYY
+/// The year must be four digits between 1970 and 2099 followed by a space, so a numeric
+/// hostname is not mistaken for it.
+fn timestamp_3164_year_after_time(
+    numeric_month: bool,
+) -> impl Fn(&str) -> IResult<&str, NaiveDateTime> {
+    move |input| {
+        map_res(
+            tuple((
+                timestamp_3164_no_year(numeric_month),
+                space1,
+                terminated(
+                    verify(
+                        map_res(
+                            take_while_m_n(4, 4, |c: char| c.is_ascii_digit()),
+                            str::parse,
+                        ),
+                        |year| (1970..=2099).contains(year),
+                    ),
+                    peek(alt((space1, eof))),
+                ),
+            )),
+            |(((month, date, hour, minute, seconds), nanos), _, year)| {
+                NaiveDate::from_ymd_opt(year, month, date)
+                    .and_then(|date| date.and_hms_nano_opt(hour, minute, seconds, nanos))
+                    .ok_or_else(|| error::Error::new(input, ErrorKind::Fail))
+            },
+        )(input)
+    }
+}
+
 /// Timestamp including year. MMM DD YYYY HH:MM:SS
 fn timestamp_3164_with_year(input: &str) -> IResult<&str, NaiveDateTime> {
     map_res(
@@ -87,6 +170,7 @@ fn timestamp_3164_with_year(input: &str) -> IResult<&str, NaiveDateTime> {
 /// and a function to resolve the year.
 fn make_timestamp<F, Tz: TimeZone>(
     idate: IncompleteDate,
+    nanos: u32,
     get_year: F,
     tz: Option<Tz>,
 ) -> Option<DateTime<FixedOffset>>
@@ -108,42 +192,89 @@ where
             .earliest()
             .map(Into::into),
     }
+    .and_then(|datetime| datetime.with_nanosecond(nanos))
+}
+
+/// Give a naive datetime the timezone, or local time if no timezone is specified.
+fn with_timezone<Tz: TimeZone>(naive_date: NaiveDateTime, tz: Option<Tz>) -> DateTime<FixedOffset> {
+    match tz {
+        Some(tz) => {
+            let offset = tz.offset_from_utc_datetime(&naive_date).fix();
+            DateTime::<FixedOffset>::from_naive_utc_and_offset(naive_date, offset)
+        }
+        None => match Local.from_local_datetime(&naive_date).earliest() {
+            Some(timestamp) => timestamp.into(),
+            None => Local.from_utc_datetime(&naive_date).into(),
+        },
+    }
+}
+
+/// Parse a timestamp in square brackets, as some applications put at the start of the message.
+/// eg. `[2021-06-08 11:54:08]` or `[2021-06-08T11:54:08.123+01:00]`.
+/// Any whitespace following the closing bracket is consumed.
+pub(crate) fn bracketed_timestamp<Tz: TimeZone + Copy>(
+    tz: Option<Tz>,
+) -> impl Fn(&str) -> IResult<&str, DateTime<FixedOffset>> {
+    move |input| {
+        terminated(
+            delimited(
+                tag("["),
+                map_res(take_until("]"), |timestamp: &str| {
+                    DateTime::parse_from_rfc3339(timestamp).or_else(|_| {
+                        NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f")
+                            .or_else(|_| {
+                                NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f")
+                            })
+                            .map(|naive_date| with_timezone(naive_date, tz))
+                    })
+                }),
+                tag("]"),
+            ),
+            space0,
+        )(input)
+    }
 }
 
 /// Parse the timestamp in the format specified in RFC3164,
 /// either with year or without.
-/// MMM DD HH:MM:SS or MMM DD YYYY HH:MM:SS
+/// MMM DD HH:MM:SS, MMM DD YYYY HH:MM:SS or MMM DD HH:MM:SS YYYY
+/// An RFC3339 timestamp is also accepted unless the `strict_rfc3164_timestamp` option is set.
 //
 /// # Arguments
 ///
 /// * get_year - a function that is called if the parsed message contains a date with no year.
-///              the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
+///   the function takes a (month, date, hour, minute, second) tuple and should return the year to use.
 /// * tz - An optional timezone.
-///        If None is specified and the parsed date doesn't specify a timezone the date is parsed in time local time.
+///   If None is specified and the parsed date doesn't specify a timezone the date is parsed in time local time.
+/// * options - the options that control which formats are accepted.
 ///
 pub(crate) fn timestamp_3164<F, Tz: TimeZone + Copy>(
     get_year: F,
     tz: Option<Tz>,
+    options: ParseOptions,
 ) -> impl Fn(&str) -> IResult<&str, DateTime<FixedOffset>>
 where
     F: FnOnce(IncompleteDate) -> i32 + Copy,
 {
     move |input| {
         alt((
-            map_res(timestamp_3164_no_year, |ts| {
-                make_timestamp::<_, Tz>(ts, get_year, tz).ok_or("invalid date")
-            }),
-            map(timestamp_3164_with_year, |naive_date| match tz {
-                Some(tz) => {
-                    let offset = tz.offset_from_utc_datetime(&naive_date).fix();
-                    DateTime::<FixedOffset>::from_utc(naive_date, offset)
-                }
-                None => match Local.from_local_datetime(&naive_date).earliest() {
-                    Some(timestamp) => timestamp.into(),
-                    None => Local.from_utc_datetime(&naive_date).into(),
+            map(
+                timestamp_3164_year_after_time(options.numeric_month),
+                |naive_date| with_timezone(naive_date, tz),
+            ),
+            map_res(
+                timestamp_3164_no_year(options.numeric_month),
+                |(ts, nanos)| {
+                    make_timestamp::<_, Tz>(ts, nanos, get_year, tz).ok_or("invalid date")
                 },
+            ),
+            map(timestamp_3164_with_year, |naive_date| {
+                with_timezone(naive_date, tz)
             }),
-            timestamp_3339,
+            map_opt(
+                cond(!options.strict_rfc3164_timestamp, timestamp_3339),
+                |timestamp| timestamp,
+            ),
         ))(input)
     }
 }
@@ -193,26 +324,155 @@ mod tests {
         )
     }
 
+    #[test]
+    fn parse_timestamp_3339_fraction_separator() {
+        let expected = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2019, 2, 13, 21, 53, 30)
+            .unwrap()
+            + Duration::microseconds(605850);
+
+        assert_eq!(
+            timestamp_3339("2019-02-13T21:53:30.605850+00:00 ").unwrap(),
+            (" ", expected)
+        );
+        assert_eq!(
+            timestamp_3339("2019-02-13T21:53:30,605850+00:00 ").unwrap(),
+            (" ", expected)
+        );
+        assert!(timestamp_3339("2019-02-13T21:53:30,605,850+00:00 ").is_err());
+    }
+
+    #[test]
+    fn parse_timestamp_3339_truncates_excess_precision() {
+        assert_eq!(
+            timestamp_3339("2003-10-11T22:14:15.123456789012Z ").unwrap(),
+            (
+                " ",
+                FixedOffset::east_opt(0)
+                    .unwrap()
+                    .with_ymd_and_hms(2003, 10, 11, 22, 14, 15)
+                    .unwrap()
+                    + Duration::nanoseconds(123_456_789)
+            )
+        );
+    }
+
     #[test]
     fn parse_timestamp_3164() {
         assert_eq!(
-            timestamp_3164_no_year("Dec 28 16:49:07 ").unwrap(),
-            (" ", (12, 28, 16, 49, 7))
+            timestamp_3164_no_year(false)("Dec 28 16:49:07 ").unwrap(),
+            (" ", ((12, 28, 16, 49, 7), 0))
+        );
+    }
+
+    #[test]
+    fn parse_timestamp_3164_strict() {
+        let expected = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2020, 10, 11, 22, 14, 15)
+            .unwrap();
+        assert_eq!(
+            timestamp_3164(|_| 2020, Some(Utc.fix()), ParseOptions::default())(
+                "2020-10-11T22:14:15Z "
+            )
+            .unwrap(),
+            (" ", expected)
+        );
+        let strict = ParseOptions {
+            strict_rfc3164_timestamp: true,
+            ..Default::default()
+        };
+        assert!(
+            timestamp_3164(|_| 2020, Some(Utc.fix()), strict)("2020-10-11T22:14:15Z ").is_err()
+        );
+        assert_eq!(
+            timestamp_3164(|_| 2020, Some(Utc.fix()), strict)("Oct 11 22:14:15 ").unwrap(),
+            (" ", expected)
         );
     }
 
+    #[test]
+    fn parse_timestamp_3164_numeric_month() {
+        assert_eq!(
+            timestamp_3164_no_year(true)("01 05 15:33:03 ").unwrap(),
+            (" ", ((1, 5, 15, 33, 3), 0))
+        );
+        assert_eq!(
+            timestamp_3164_no_year(true)("Jan 05 15:33:03 ").unwrap(),
+            (" ", ((1, 5, 15, 33, 3), 0))
+        );
+        assert!(timestamp_3164_no_year(true)("13 05 15:33:03 ").is_err());
+        assert!(timestamp_3164_no_year(true)("1 05 15:33:03 ").is_err());
+        assert!(timestamp_3164_no_year(false)("01 05 15:33:03 ").is_err());
+    }
+
     #[test]
     fn parse_timestamp_3164_trailing_colon() {
         assert_eq!(
-            timestamp_3164_no_year("Dec 28 16:49:07:").unwrap(),
-            ("", (12, 28, 16, 49, 7))
+            timestamp_3164_no_year(false)("Dec 28 16:49:07:").unwrap(),
+            ("", ((12, 28, 16, 49, 7), 0))
+        );
+    }
+
+    #[test]
+    fn parse_timestamp_3164_fractional_seconds() {
+        assert_eq!(
+            timestamp_3164_no_year(false)("Jan 5 15:33:03,123 ").unwrap(),
+            (" ", ((1, 5, 15, 33, 3), 123_000_000))
+        );
+
+        assert_eq!(
+            timestamp_3164_no_year(false)("Jan 5 15:33:03.123456 ").unwrap(),
+            (" ", ((1, 5, 15, 33, 3), 123_456_000))
+        );
+
+        assert_eq!(
+            timestamp_3164(|_| 2020, Some(Utc.fix()), ParseOptions::default())(
+                "Jan 5 15:33:03,123 "
+            )
+            .unwrap(),
+            (
+                " ",
+                FixedOffset::east_opt(0)
+                    .unwrap()
+                    .with_ymd_and_hms(2020, 1, 5, 15, 33, 3)
+                    .unwrap()
+                    + Duration::milliseconds(123)
+            )
+        );
+    }
+
+    #[test]
+    fn parse_bracketed_timestamp() {
+        assert_eq!(
+            bracketed_timestamp(Some(Utc))("[2021-06-08 11:54:08] hello").unwrap(),
+            (
+                "hello",
+                Utc.with_ymd_and_hms(2021, 6, 8, 11, 54, 8).unwrap().into()
+            )
+        );
+        assert_eq!(
+            bracketed_timestamp(Some(Utc))("[2021-06-08T11:54:08.5+01:00] hello").unwrap(),
+            (
+                "hello",
+                FixedOffset::east_opt(3600)
+                    .unwrap()
+                    .with_ymd_and_hms(2021, 6, 8, 11, 54, 8)
+                    .unwrap()
+                    + Duration::milliseconds(500)
+            )
         );
+        assert!(bracketed_timestamp(Some(Utc))("[info] hello").is_err());
     }
 
     #[test]
     fn parse_timestamp_with_year_3164() {
         assert_eq!(
-            timestamp_3164(|_| 2019, Some(Utc.fix()))("Dec 28 2008 16:49:07 ",).unwrap(),
+            timestamp_3164(|_| 2019, Some(Utc.fix()), ParseOptions::default())(
+                "Dec 28 2008 16:49:07 ",
+            )
+            .unwrap(),
             (
                 " ",
                 FixedOffset::west_opt(0)
@@ -223,6 +483,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_timestamp_year_after_time_3164() {
+        assert_eq!(
+            timestamp_3164(|_| 2019, Some(Utc.fix()), ParseOptions::default())(
+                "Jan 5 15:33:03 2020 host"
+            )
+            .unwrap(),
+            (
+                " host",
+                FixedOffset::west_opt(0)
+                    .unwrap()
+                    .with_ymd_and_hms(2020, 1, 5, 15, 33, 3)
+                    .unwrap()
+            )
+        );
+
+        // A numeric hostname is not taken to be the year.
+        assert_eq!(
+            timestamp_3164(|_| 2019, Some(Utc.fix()), ParseOptions::default())(
+                "Jan 5 15:33:03 20201 host"
+            )
+            .unwrap(),
+            (
+                " 20201 host",
+                FixedOffset::west_opt(0)
+                    .unwrap()
+                    .with_ymd_and_hms(2019, 1, 5, 15, 33, 3)
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            timestamp_3164(|_| 2019, Some(Utc.fix()), ParseOptions::default())(
+                "Oct 11 22:14:15 1234 app: msg"
+            )
+            .unwrap(),
+            (
+                " 1234 app: msg",
+                FixedOffset::west_opt(0)
+                    .unwrap()
+                    .with_ymd_and_hms(2019, 10, 11, 22, 14, 15)
+                    .unwrap()
+            )
+        );
+    }
+
     #[test]
     fn parse_timestamp_no_year_3164_local_time() {
         let offset = Local
@@ -234,7 +539,8 @@ mod tests {
             )
             .unwrap();
         assert_eq!(
-            timestamp_3164::<_, Local>(|_| 2019, None)("Aug 4 16:49:07 ",).unwrap(),
+            timestamp_3164::<_, Local>(|_| 2019, None, ParseOptions::default())("Aug 4 16:49:07 ",)
+                .unwrap(),
             (" ", offset.with_ymd_and_hms(2019, 8, 4, 16, 49, 7).unwrap())
         );
     }
@@ -250,7 +556,10 @@ mod tests {
             )
             .unwrap();
         assert_eq!(
-            timestamp_3164::<_, Local>(|_| 2019, None)("Aug 4 2020 16:49:07 ",).unwrap(),
+            timestamp_3164::<_, Local>(|_| 2019, None, ParseOptions::default())(
+                "Aug 4 2020 16:49:07 ",
+            )
+            .unwrap(),
             (" ", offset.with_ymd_and_hms(2020, 8, 4, 16, 49, 7).unwrap())
         );
     }
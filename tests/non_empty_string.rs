@@ -1,10 +1,6 @@
 use quickcheck::{Arbitrary, Gen};
 use std::num::NonZeroU8;
 
-trait FilterChars {
-    fn valid(c: char) -> bool;
-}
-
 fn gen_string<F>(g: &mut Gen, valid_char: F) -> String
 where
     F: Fn(char) -> bool,
@@ -74,10 +70,6 @@ macro_rules! arbitrary_string {
     };
 }
 
-arbitrary_string!(NonEmptyString, |c: char| {
-    !c.is_whitespace() && !c.is_control() && c.is_ascii()
-});
-
 // Structured data names cannot contain ] = or whitespace
 arbitrary_string!(NameString, |c: char| {
     !c.is_whitespace() && !c.is_control() && c.is_ascii() && c != ']' && c != '=' && c != '-'
@@ -13,7 +13,8 @@ use non_empty_string::{
 };
 use quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
 use syslog_loose::{
-    decompose_pri, parse_message, Message, ProcId, Protocol, StructuredElement, Variant,
+    decompose_pri, escape_param_value, parse_message, Message, ProcId, Protocol, StructuredElement,
+    Variant,
 };
 
 /// Create a wrapper struct for us to implement Arbitrary against
@@ -90,12 +91,15 @@ impl Arbitrary for Wrapper<Message<String>> {
             facility,
             severity,
             timestamp: Some(Utc.timestamp_opt(secs as i64, 0).unwrap().into()),
+            raw_timestamp: None,
             hostname,
             appname,
             procid,
             msgid,
             protocol,
             structured_data: structured_data.iter().map(|s| s.clone().unwrap()).collect(),
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: msg.trim().into(),
         })
     }
@@ -136,6 +140,7 @@ impl Arbitrary for Wrapper<Message<String>> {
                             facility,
                             severity,
                             timestamp,
+                            raw_timestamp: None,
                             hostname: hostname.clone().map(|s| s.get_str()),
                             appname: appname.clone().map(|s| s.get_str()),
                             procid: procid.clone().map(|s| s.unwrap()),
@@ -145,6 +150,8 @@ impl Arbitrary for Wrapper<Message<String>> {
                                 .iter()
                                 .map(|s| s.clone().unwrap())
                                 .collect(),
+                            unparsed_structured_data: vec![],
+                            had_bom: false,
                             msg: msg.trim().into(),
                         })
                     },
@@ -163,9 +170,9 @@ impl Arbitrary for Wrapper<ProcId<String>> {
                 let ProcIdString(inner) = &name;
                 // A `ProdIdString` is ambiguous to the parser if it's all digit
                 // characters, like "8". We have try to parse the result into an
-                // i32 and if it succeeds then this generated ProcIdString will
+                // i64 and if it succeeds then this generated ProcIdString will
                 // be confused for a ProdId::PID on parsing.
-                let is_ambiguous = inner.parse::<i32>().is_ok();
+                let is_ambiguous = inner.parse::<i64>().is_ok();
                 if !is_ambiguous {
                     break ProcId::Name(name.get_str());
                 }
@@ -210,7 +217,7 @@ impl Arbitrary for Wrapper<StructuredElement<String>> {
                 element
                     .params
                     .iter()
-                    .map(|(name, value)| ((NameString(name.clone()), ValueString(value.clone()))))
+                    .map(|(name, value)| (NameString(name.clone()), ValueString(value.clone())))
                     .collect(),
             )
                 .shrink()
@@ -231,13 +238,6 @@ impl Arbitrary for Wrapper<StructuredElement<String>> {
     }
 }
 
-// assume that Some("-") is equivalent to None
-fn is_same_hostname(expected: Option<String>, parsed: Option<String>) -> bool {
-    expected == parsed
-        || (expected == Some("-".into()) && parsed.is_none())
-        || (expected.is_none() && parsed == Some("-".into()))
-}
-
 fn inner_parses_generated_messages(msg: Wrapper<Message<String>>) -> TestResult {
     let msg: Message<String> = msg.unwrap();
 
@@ -247,7 +247,8 @@ fn inner_parses_generated_messages(msg: Wrapper<Message<String>>) -> TestResult
     // Parse it.
     let parsed: Message<&str> = parse_message(&text, Variant::Either);
     let parsed = parsed.into();
-    let result = msg == parsed;
+    // Some("-") is equivalent to None
+    let result = msg.eq_nilaware(&parsed);
 
     if !result {
         println!("msg: {:#?}\ntext: {}\nparsed: {:#?}", msg, text, parsed);
@@ -257,7 +258,6 @@ fn inner_parses_generated_messages(msg: Wrapper<Message<String>>) -> TestResult
     assert_eq!(msg.facility, parsed.facility);
     assert_eq!(msg.severity, parsed.severity);
     assert_eq!(msg.timestamp, parsed.timestamp);
-    assert!(is_same_hostname(msg.hostname, parsed.hostname));
     assert_eq!(msg.appname, parsed.appname);
     assert_eq!(msg.procid, parsed.procid);
     assert_eq!(msg.msgid, parsed.msgid);
@@ -276,3 +276,38 @@ fn parses_generated_messages() {
         .max_tests(10_000)
         .quickcheck(inner_parses_generated_messages as fn(Wrapper<Message<String>>) -> TestResult);
 }
+
+fn inner_escaped_param_values_round_trip(value: String) -> TestResult {
+    let element = StructuredElement {
+        id: "id".to_string(),
+        params: vec![("key".to_string(), escape_param_value(&value))],
+    };
+
+    let text = format!(
+        "<13>1 2019-02-13T19:48:34+00:00 host app - - {} msg",
+        element
+    );
+    let parsed = parse_message(&text, Variant::RFC5424);
+
+    let unescaped = parsed
+        .structured_data
+        .first()
+        .and_then(|element| element.params().next())
+        .map(|(_, value)| value);
+
+    if unescaped.as_ref() != Some(&value) {
+        println!(
+            "value: {:?}\ntext: {}\nparsed: {:?}",
+            value, text, unescaped
+        );
+    }
+
+    TestResult::from_bool(unescaped == Some(value))
+}
+
+#[test]
+fn escaped_param_values_round_trip() {
+    QuickCheck::new()
+        .tests(1_000)
+        .quickcheck(inner_escaped_param_values_round_trip as fn(String) -> TestResult);
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "serde")]
+
+use syslog_loose::{parse_message, Message, SyslogFacility, SyslogSeverity, Variant};
+
+#[test]
+fn serialize_message() {
+    let raw = r#"<13>1 2019-02-13T19:48:34.123+01:00 74794bfb6795 root 8449 - [meta sequenceId="1"] i am foobar"#;
+    let message = parse_message(raw, Variant::Either);
+    let json = serde_json::to_value(&message).unwrap();
+
+    assert_eq!(json["facility"], "user");
+    assert_eq!(json["severity"], "notice");
+    assert_eq!(json["timestamp"], "2019-02-13T19:48:34.123+01:00");
+    assert_eq!(json["hostname"], "74794bfb6795");
+    assert_eq!(json["procid"]["PID"], 8449);
+    assert_eq!(json["structured_data"][0]["id"], "meta");
+    assert_eq!(json["msg"], "i am foobar");
+}
+
+#[test]
+fn round_trip_message() {
+    let raw = r#"<13>1 2019-02-13T19:48:34.123+00:00 74794bfb6795 root 8449 - [meta sequenceId="1"] i am foobar"#;
+    let message: Message<String> = parse_message(raw, Variant::Either).into();
+    let json = serde_json::to_string(&message).unwrap();
+
+    assert_eq!(
+        serde_json::from_str::<Message<String>>(&json).unwrap(),
+        message
+    );
+}
+
+#[test]
+fn deserialize_facility_severity() {
+    assert_eq!(
+        serde_json::from_str::<SyslogFacility>(r#""local7""#).unwrap(),
+        SyslogFacility::LOG_LOCAL7
+    );
+    assert_eq!(
+        serde_json::from_str::<SyslogSeverity>(r#""warning""#).unwrap(),
+        SyslogSeverity::SEV_WARNING
+    );
+    assert!(serde_json::from_str::<SyslogSeverity>(r#""loud""#).is_err());
+}
@@ -1,7 +1,14 @@
 use chrono::{prelude::*, Duration};
 use syslog_loose::{
-    parse_message, parse_message_with_year, parse_message_with_year_exact,
-    parse_message_with_year_exact_tz, IncompleteDate, Message, ProcId, Protocol, StructuredElement,
+    parse_message, parse_message_bytes, parse_message_if, parse_message_no_trim,
+    parse_message_owned, parse_message_require_sd, parse_message_with_consumed,
+    parse_message_with_limits, parse_message_with_metadata, parse_message_with_options,
+    parse_message_with_options_owned, parse_message_with_stats, parse_message_with_year,
+    parse_message_with_year_exact, parse_message_with_year_exact_detailed,
+    parse_message_with_year_exact_tz, parse_message_with_year_owned, parse_message_with_year_tz,
+    parse_message_with_year_tz_ctx, parse_message_with_year_tz_options,
+    parse_message_with_year_tz_owned, parse_with_options, Framing, IncompleteDate, Message, Origin,
+    ParseError, ParseMetadata, ParseOptions, ParseStats, ProcId, Protocol, StructuredElement,
     SyslogFacility, SyslogSeverity, Variant,
 };
 
@@ -24,12 +31,15 @@ fn parse_nginx() {
             facility: Some(SyslogFacility::LOG_LOCAL7),
             severity: Some(SyslogSeverity::SEV_INFO),
             timestamp: Some(Local.with_ymd_and_hms(2019, 12, 28,16, 49, 7).unwrap().into()),
+            raw_timestamp: Some("Dec 28 16:49:07"),
             hostname: Some("plertrood-thinkpad-x220"),
             appname: Some("nginx"),
             procid: None,
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "127.0.0.1 - - [28/Dec/2019:16:49:07 +0000] \"GET / HTTP/1.1\" 304 0 \"-\" \"Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:71.0) Gecko/20100101 Firefox/71.0\"",
         }
     );
@@ -56,12 +66,15 @@ fn parse_chrono_tz() {
                     .with_ymd_and_hms(2020, 1, 5, 15, 33, 3)
                     .unwrap()
             ),
+            raw_timestamp: Some("Jan  5 15:33:03"),
             hostname: Some("plertrood-ThinkPad-X220"),
             appname: Some("rsyslogd"),
             procid: None,
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "start",
         }
     );
@@ -83,6 +96,7 @@ fn parse_rsyslog() {
                     .unwrap()
                     .into()
             ),
+            raw_timestamp: Some("Jan  5 15:33:03"),
             hostname: Some("plertrood-ThinkPad-X220"),
             appname: Some("rsyslogd"),
             procid: None,
@@ -97,6 +111,8 @@ fn parse_rsyslog() {
                     ("x-info", "http://www.rsyslog.com"),
                 ]
             }],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "start",
         }
     );
@@ -117,12 +133,15 @@ fn parse_haproxy() {
                     .unwrap()
                     .into()
             ),
+            raw_timestamp: Some("Jan 13 16:33:35"),
             hostname: None,
             appname: Some("haproxy"),
             procid: Some(ProcId::PID(73411)),
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "Proxy sticky-servers started.",
         }
     );
@@ -144,12 +163,15 @@ fn parse_5424_no_structured_data() {
                     .unwrap()
                     + Duration::milliseconds(3)
             ),
+            raw_timestamp: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("mymachine.example.com"),
             appname: Some("su"),
             procid: None,
             msgid: Some("ID47"),
             protocol: Protocol::RFC5424(1),
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "BOM'su root' failed for lonvick on /dev/pts/8",
         }
     );
@@ -171,6 +193,7 @@ fn parse_5424_structured_data() {
                     .unwrap()
                     + Duration::milliseconds(3)
             ),
+            raw_timestamp: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("mymachine.example.com"),
             appname: Some("evntslog"),
             procid: None,
@@ -184,6 +207,8 @@ fn parse_5424_structured_data() {
                     ("eventID", "1011")
                 ]
             },],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "BOMAn application event log entry...",
         }
     );
@@ -205,6 +230,7 @@ fn parse_5424_empty_structured_data() {
                     .unwrap()
                     + Duration::milliseconds(3)
             ),
+            raw_timestamp: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("mymachine.example.com"),
             appname: Some("evntslog"),
             procid: None,
@@ -214,6 +240,8 @@ fn parse_5424_empty_structured_data() {
                 id: "exampleSDID@32473",
                 params: vec![("iut", "3"), ("eventSource", ""), ("eventID", "1011")]
             },],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "BOMAn application event log entry...",
         }
     );
@@ -235,6 +263,7 @@ fn parse_5424_multiple_structured_data() {
                     .unwrap()
                     + Duration::milliseconds(3)
             ),
+            raw_timestamp: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("mymachine.example.com"),
             appname: Some("evntslog"),
             procid: None,
@@ -254,6 +283,8 @@ fn parse_5424_multiple_structured_data() {
                     params: vec![("class", "high"),]
                 }
             ],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "BOMAn application event log entry...",
         }
     );
@@ -271,12 +302,15 @@ fn parse_3164_invalid_structured_data() {
             facility: Some(SyslogFacility::LOG_SYSLOG),
             severity: Some(SyslogSeverity::SEV_INFO),
             timestamp: Some(Local.with_ymd_and_hms(2020, 1, 5, 15, 33, 3).unwrap().into()),
+            raw_timestamp: Some("Jan  5 15:33:03"),
             hostname: Some("plertrood-ThinkPad-X220"),
             appname: Some("rsyslogd"),
             procid: None,
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "[software=\"rsyslogd\" swVersion=\"8.32.0\" x-pid=\"20506\" x-info=\"http://www.rsyslog.com\"] start",
         }
     );
@@ -292,12 +326,15 @@ fn parse_3164_no_tag() {
             facility: Some(SyslogFacility::LOG_SYSLOG),
             severity: Some(SyslogSeverity::SEV_INFO),
             timestamp: Some(Local.with_ymd_and_hms(2020, 1, 5,15, 33, 3).unwrap().into()),
+            raw_timestamp: Some("Jan  5 15:33:03"),
             hostname: Some("plertrood-ThinkPad-X220"),
             appname: None,
             procid: None,
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "[software=\"rsyslogd\" swVersion=\"8.32.0\" x-pid=\"20506\" x-info=\"http://www.rsyslog.com\"] start",
         }
     );
@@ -313,12 +350,15 @@ fn parse_european_chars() {
             facility: Some(SyslogFacility::LOG_SYSLOG),
             severity: Some(SyslogSeverity::SEV_INFO),
             timestamp: Some(Local.with_ymd_and_hms(2020, 1, 5, 10, 1, 0).unwrap().into()),
+            raw_timestamp: Some("Jan 5 10:01:00"),
             hostname: Some("Übergröße"),
             appname: Some("außerplanmäßig"),
             procid: None,
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "größenordnungsmäßig",
         }
     );
@@ -334,12 +374,15 @@ fn parse_invalid_message() {
             facility: None,
             severity: None,
             timestamp: None,
+            raw_timestamp: None,
             hostname: None,
             appname: None,
             procid: None,
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "complete and utter gobbledegook",
         }
     );
@@ -356,12 +399,15 @@ fn parse_blank_msg() {
                 .with_ymd_and_hms(1969, 12, 3, 23, 58, 58)
                 .unwrap(),
         ),
+        raw_timestamp: None,
         hostname: None,
         appname: None,
         procid: None,
         msgid: None,
         protocol: Protocol::RFC5424(1),
         structured_data: vec![],
+        unparsed_structured_data: vec![],
+        had_bom: false,
         msg: "",
     };
 
@@ -379,12 +425,15 @@ fn parse_blank_msg() {
                     .with_ymd_and_hms(1969, 12, 3, 23, 58, 58)
                     .unwrap(),
             ),
+            raw_timestamp: Some("1969-12-03T23:58:58+00:00"),
             hostname: None,
             appname: None,
             procid: None,
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "",
         }
     );
@@ -418,6 +467,7 @@ fn syslog_ng_network_syslog_protocol() {
                     .with_ymd_and_hms(2019, 2, 13, 19, 48, 34)
                     .unwrap()
             ),
+            raw_timestamp: Some("2019-02-13T19:48:34+00:00"),
             hostname: Some("74794bfb6795"),
             appname: Some("root"),
             procid: Some(ProcId::PID(8449)),
@@ -433,9 +483,23 @@ fn syslog_ng_network_syslog_protocol() {
                     params: vec![("ip", "192.168.0.1"), ("software", "test"),]
                 }
             ],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "i am foobar",
         }
-    )
+    );
+
+    assert_eq!(parse_message(&raw, Variant::Either).sys_uptime(), Some(37));
+    assert_eq!(parse_message(&raw, Variant::Either).sequence_id(), Some(1));
+}
+
+#[test]
+fn parse_sequence_id() {
+    let raw = r#"<13>1 2019-02-13T19:48:34+00:00 host root 8449 - [meta@123 sequenceId="1"][meta sequenceId="x"] msg"#;
+    assert_eq!(parse_message(raw, Variant::Either).sequence_id(), None);
+
+    let raw = r#"<13>1 2019-02-13T19:48:34+00:00 host root 8449 - [meta language="EN"] msg"#;
+    assert_eq!(parse_message(raw, Variant::Either).sequence_id(), None);
 }
 
 #[test]
@@ -445,7 +509,7 @@ fn handles_incorrect_sd_element() {
         r#"[incorrect x]"#
     );
 
-    let should = Message {
+    let mut should = Message {
         facility: Some(SyslogFacility::LOG_USER),
         severity: Some(SyslogSeverity::SEV_NOTICE),
         timestamp: Some(
@@ -454,12 +518,15 @@ fn handles_incorrect_sd_element() {
                 .with_ymd_and_hms(2019, 2, 13, 19, 48, 34)
                 .unwrap(),
         ),
+        raw_timestamp: Some("2019-02-13T19:48:34+00:00"),
         hostname: Some("74794bfb6795"),
         appname: Some("root"),
         procid: Some(ProcId::PID(8449)),
         msgid: None,
         protocol: Protocol::RFC5424(1),
         structured_data: vec![],
+        unparsed_structured_data: vec!["[incorrect x]"],
+        had_bom: false,
         msg: "qwerty",
     };
 
@@ -470,9 +537,61 @@ fn handles_incorrect_sd_element() {
         r#"[incorrect x=]"#
     );
 
+    should.unparsed_structured_data = vec!["[incorrect x=]"];
     assert_eq!(parse_message(&msg, Variant::Either), should);
 }
 
+#[test]
+fn parse_with_consumed() {
+    let msg = "<34>1 2003-10-11T22:14:15.003Z host su - ID47 - message\n";
+    let (message, consumed) = parse_message_with_consumed(msg, Variant::Either);
+    assert_eq!(message.msg, "message");
+    assert_eq!(consumed, msg.len());
+
+    let msg = "not a syslog message";
+    let (message, consumed) = parse_message_with_consumed(msg, Variant::RFC5424);
+    assert_eq!(message.msg, msg);
+    assert_eq!(consumed, msg.len());
+
+    let first = "<34>1 2003-10-11T22:14:15.003Z host su - ID47 - first message\n";
+    let input = format!(
+        "{}<34>1 2003-10-11T22:14:15.003Z host su - ID47 - second",
+        first
+    );
+    let (message, consumed) = parse_message_with_consumed(&input, Variant::Either);
+    assert_eq!(message.msg, "first message");
+    assert_eq!(consumed, first.len());
+    assert!(consumed < input.len());
+
+    let (message, consumed) = parse_message_with_consumed(&input[consumed..], Variant::Either);
+    assert_eq!(message.msg, "second");
+    assert_eq!(consumed, input.len() - first.len());
+
+    let second = "<34>1 2003-10-11T22:14:15.003Z host su - ID47 - octet counted";
+    let input = format!("{} {}{}", second.len(), second, first);
+    let (message, consumed) = parse_message_with_consumed(&input, Variant::Either);
+    assert_eq!(message.msg, "octet counted");
+    assert_eq!(consumed, input.len() - first.len());
+}
+
+#[test]
+fn count_dropped_sd_elements() {
+    let msg = r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [incorrect x][meta sequenceId="1"] qwerty"#;
+    let (message, stats) = parse_message_with_stats(msg, Variant::Either);
+    assert_eq!(message.structured_data.len(), 1);
+    assert_eq!(
+        stats,
+        ParseStats {
+            dropped_structured_data: 1
+        }
+    );
+
+    let msg =
+        r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [meta sequenceId="1"] qwerty"#;
+    let (_, stats) = parse_message_with_stats(msg, Variant::Either);
+    assert_eq!(stats.dropped_structured_data, 0);
+}
+
 #[test]
 fn handles_empty_sd_element() {
     let msg = format!(
@@ -491,6 +610,7 @@ fn handles_empty_sd_element() {
                     .with_ymd_and_hms(2019, 2, 13, 19, 48, 34)
                     .unwrap()
             ),
+            raw_timestamp: Some("2019-02-13T19:48:34+00:00"),
             hostname: Some("74794bfb6795"),
             appname: Some("root"),
             procid: Some(ProcId::PID(8449)),
@@ -500,6 +620,8 @@ fn handles_empty_sd_element() {
                 id: "empty",
                 params: vec![]
             }],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "qwerty",
         }
     );
@@ -520,6 +642,7 @@ fn handles_empty_sd_element() {
                     .with_ymd_and_hms(2019, 2, 13, 19, 48, 34)
                     .unwrap()
             ),
+            raw_timestamp: Some("2019-02-13T19:48:34+00:00"),
             hostname: Some("74794bfb6795"),
             appname: Some("root"),
             procid: Some(ProcId::PID(8449)),
@@ -535,6 +658,8 @@ fn handles_empty_sd_element() {
                     params: vec![]
                 },
             ],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "qwerty",
         }
     );
@@ -555,6 +680,7 @@ fn handles_empty_sd_element() {
                     .with_ymd_and_hms(2019, 2, 13, 19, 48, 34)
                     .unwrap()
             ),
+            raw_timestamp: Some("2019-02-13T19:48:34+00:00"),
             hostname: Some("74794bfb6795"),
             appname: Some("root"),
             procid: Some(ProcId::PID(8449)),
@@ -570,6 +696,8 @@ fn handles_empty_sd_element() {
                     params: vec![("x", "1")]
                 },
             ],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "qwerty",
         }
     );
@@ -590,6 +718,7 @@ fn handles_empty_sd_element() {
                     .with_ymd_and_hms(2019, 2, 13, 19, 48, 34)
                     .unwrap()
             ),
+            raw_timestamp: Some("2019-02-13T19:48:34+00:00"),
             hostname: Some("74794bfb6795"),
             appname: Some("root"),
             procid: Some(ProcId::PID(8449)),
@@ -599,6 +728,8 @@ fn handles_empty_sd_element() {
                 id: "empty",
                 params: vec![("not_really", "testing the test")]
             },],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "qwerty",
         }
     );
@@ -618,6 +749,17 @@ fn handles_weird_whitespace() {
     );
 }
 
+#[test]
+fn rsyslog_file_format_trailing_newline() {
+    let raw = "<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - - i am foobar\n";
+
+    assert_eq!(parse_message(raw, Variant::Either).msg, "i am foobar");
+    assert_eq!(
+        parse_message_no_trim(raw, Variant::Either).msg,
+        "i am foobar\n"
+    );
+}
+
 #[test]
 fn syslog_ng_default_network() {
     let raw = r#"<13>Feb 13 20:07:26 74794bfb6795 root[8539]: i am foobar"#;
@@ -633,12 +775,15 @@ fn syslog_ng_default_network() {
                     .unwrap()
                     .into()
             ),
+            raw_timestamp: Some("Feb 13 20:07:26"),
             hostname: Some("74794bfb6795"),
             appname: Some("root"),
             procid: Some(ProcId::PID(8539)),
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "i am foobar",
         }
     );
@@ -659,6 +804,7 @@ fn rsyslog_omfwd_tcp_default() {
                     .unwrap()
                     .into()
             ),
+            raw_timestamp: Some("Feb 13 21:31:56"),
             hostname: Some("74794bfb6795"),
             appname: Some("liblogging-stdlog"),
             procid: None,
@@ -673,6 +819,8 @@ fn rsyslog_omfwd_tcp_default() {
                     ("x-info", "http://www.rsyslog.com")
                 ]
             }],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "start",
         }
     );
@@ -694,6 +842,7 @@ fn rsyslog_omfwd_tcp_forward_format() {
                     .unwrap()
                     + Duration::microseconds(605_850)
             ),
+            raw_timestamp: Some("2019-02-13T21:53:30.605850+00:00"),
             hostname: Some("74794bfb6795"),
             appname: Some("liblogging-stdlog"),
             procid: None,
@@ -708,6 +857,8 @@ fn rsyslog_omfwd_tcp_forward_format() {
                     ("x-info", "http://www.rsyslog.com")
                 ]
             }],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "start",
         }
     );
@@ -726,17 +877,73 @@ fn logical_system_juniper_routers() {
                 FixedOffset::west_opt(1800 * 6).unwrap()
                     .with_ymd_and_hms(2020, 5, 22,14, 59, 9).unwrap() + Duration::microseconds(250000)
             ),
+            raw_timestamp: Some("2020-05-22T14:59:09.250-03:00"),
             hostname: Some("OX-XXX-MX204"),
             appname: Some("OX-XXX-CONTEUDO:rpd"),
             procid: Some(ProcId::PID(6589)),
             msgid: None,
             protocol: Protocol::RFC5424(1),
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "bgp_listen_accept: %DAEMON-4: Connection attempt from unconfigured neighbor: 2001:XXX::219:166+57284",
         }
     );
 }
 
+#[test]
+fn extract_ips_from_msg() {
+    // The address in the juniper message has been anonymised, so isn't a valid ip.
+    let raw = r#"<28>1 2020-05-22T14:59:09.250-03:00 OX-XXX-MX204 OX-XXX-CONTEUDO:rpd 6589 - - bgp_listen_accept: %DAEMON-4: Connection attempt from unconfigured neighbor: 2001:XXX::219:166+57284"#;
+    let parsed = parse_message_with_year(raw, with_year, Variant::Either);
+    assert!(parsed.extract_ips().is_empty());
+
+    let raw = raw.replace("2001:XXX::219:166", "2001:db8::219:166");
+    let parsed = parse_message_with_year(&raw, with_year, Variant::Either);
+    assert_eq!(
+        parsed.extract_ips(),
+        vec!["2001:db8::219:166".parse::<std::net::IpAddr>().unwrap()]
+    );
+
+    let parsed = parse_message(
+        "<34>Oct 11 22:14:15 host sshd: Failed password from 192.0.2.1:22, then 198.51.100.7.",
+        Variant::Either,
+    );
+    assert_eq!(
+        parsed.extract_ips(),
+        vec![
+            std::net::IpAddr::from([192, 0, 2, 1]),
+            std::net::IpAddr::from([198, 51, 100, 7])
+        ]
+    );
+}
+
+#[test]
+fn parse_raw_timestamp() {
+    let raw = "<190>2019-02-13T21:53:30.605850+00:00 74794bfb6795 liblogging-stdlog: start";
+    let parsed = parse_message(raw, Variant::Either);
+    assert_eq!(
+        parsed.raw_timestamp,
+        Some("2019-02-13T21:53:30.605850+00:00")
+    );
+
+    let raw = "<13>Feb  5 20:07:26: 74794bfb6795 root[8539]: i am foobar";
+    let parsed = parse_message(raw, Variant::Either);
+    assert_eq!(parsed.raw_timestamp, Some("Feb  5 20:07:26"));
+
+    let raw = "<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - - i am foobar";
+    let parsed: Message<String> = parse_message(raw, Variant::Either).into();
+    assert_eq!(
+        parsed.raw_timestamp.as_deref(),
+        Some("2019-02-13T19:48:34+00:00")
+    );
+
+    assert_eq!(
+        parse_message("i am foobar", Variant::Either).raw_timestamp,
+        None
+    );
+}
+
 #[test]
 fn parse_missing_pri() {
     let msg = "Dec 28 16:49:07 plertrood-thinkpad-x220 nginx: 127.0.0.1 - - [28/Dec/2019:16:49:07 +0000] \"GET / HTTP/1.1\" 304 0 \"-\" \"Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:71.0) Gecko/20100101 Firefox/71.0\"";
@@ -747,12 +954,15 @@ fn parse_missing_pri() {
             facility: None,
             severity: None,
             timestamp: Some(Local.with_ymd_and_hms(2019, 12, 28,16, 49, 7).unwrap().into()),
+            raw_timestamp: Some("Dec 28 16:49:07"),
             hostname: Some("plertrood-thinkpad-x220"),
             appname: Some("nginx"),
             procid: None,
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "127.0.0.1 - - [28/Dec/2019:16:49:07 +0000] \"GET / HTTP/1.1\" 304 0 \"-\" \"Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:71.0) Gecko/20100101 Firefox/71.0\"",
         }
     );
@@ -771,12 +981,15 @@ fn parse_missing_pri_5424() {
                 FixedOffset::west_opt(1800 * 6).unwrap()
                     .with_ymd_and_hms(2020, 5, 22,14, 59, 9).unwrap() + Duration::microseconds(250000)
             ),
+            raw_timestamp: Some("2020-05-22T14:59:09.250-03:00"),
             hostname: Some("OX-XXX-MX204"),
             appname: Some("OX-XXX-CONTEUDO:rpd"),
             procid: Some(ProcId::PID(6589)),
             msgid: None,
             protocol: Protocol::RFC5424(1),
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "bgp_listen_accept: %DAEMON-4: Connection attempt from unconfigured neighbor: 2001:XXX::219:166+57284",
         }
     );
@@ -802,12 +1015,15 @@ fn parse_exact_with_tz() {
             facility: Some(SyslogFacility::LOG_USER),
             severity: Some(SyslogSeverity::SEV_NOTICE),
             timestamp: Some(tz.with_ymd_and_hms(2020, 2, 13, 20, 7, 26).unwrap()),
+            raw_timestamp: Some("Feb 13 20:07:26"),
             hostname: Some("74794bfb6795"),
             appname: Some("root"),
             procid: Some(ProcId::PID(8539)),
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "i am foobar",
         }
     );
@@ -834,15 +1050,18 @@ fn parse_vrl() {
             timestamp: Some(
                 FixedOffset::west_opt(0)
                     .unwrap()
-                    .with_ymd_and_hms(2020, 2, 13, 20, 07, 26)
+                    .with_ymd_and_hms(2020, 2, 13, 20, 7, 26)
                     .unwrap()
             ),
+            raw_timestamp: Some("Feb 13 20:07:26"),
             hostname: Some("74794bfb6795"),
             appname: Some("root"),
             procid: Some(ProcId::PID(8539)),
             msgid: None,
             protocol: Protocol::RFC3164,
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "syslog message",
         },
         parse_message_with_year(msg, with_year, Variant::Either)
@@ -863,12 +1082,15 @@ fn parse_ipv4_hostname() {
                     .unwrap()
                     + Duration::milliseconds(3)
             ),
+            raw_timestamp: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("42.52.1.1"),
             appname: Some("su"),
             procid: None,
             msgid: Some("ID47"),
             protocol: Protocol::RFC5424(1),
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "bananas and peas",
         },
         parse_message(msg, Variant::RFC5424)
@@ -889,14 +1111,1020 @@ fn parse_ipv6_hostname() {
                     .unwrap()
                     + Duration::milliseconds(3)
             ),
+            raw_timestamp: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("::FFFF:129.144.52.38"),
             appname: Some("su"),
             procid: None,
             msgid: Some("ID47"),
             protocol: Protocol::RFC5424(1),
             structured_data: vec![],
+            unparsed_structured_data: vec![],
+            had_bom: false,
             msg: "bananas and peas",
         },
         parse_message(msg, Variant::RFC5424)
     )
 }
+
+#[test]
+fn parse_lowercase_hostname() {
+    let msg = "<34>1 2003-10-11T22:14:15.003Z HOST.EXAMPLE.COM su - ID47 - bananas and peas";
+    let options = ParseOptions {
+        lowercase_hostname: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        parse_message_with_options_owned(msg, Variant::RFC5424, options).hostname,
+        Some("host.example.com".to_string())
+    );
+
+    assert_eq!(
+        parse_message_with_options_owned(msg, Variant::RFC5424, ParseOptions::default()).hostname,
+        Some("HOST.EXAMPLE.COM".to_string())
+    );
+}
+
+#[test]
+fn parse_normalize_localhost() {
+    let options = ParseOptions {
+        normalize_localhost: true,
+        ..Default::default()
+    };
+
+    for hostname in ["127.0.0.1", "::1", "localhost.localdomain", "LOCALHOST"] {
+        let msg = format!(
+            "<34>1 2003-10-11T22:14:15.003Z {} su - ID47 - msg",
+            hostname
+        );
+        assert_eq!(
+            parse_message_with_options(&msg, Variant::RFC5424, options).hostname,
+            Some("localhost")
+        );
+    }
+
+    let msg = "<13>Feb 13 20:07:26 127.0.0.1 app: msg";
+    assert_eq!(
+        parse_message_with_options(msg, Variant::Either, options).hostname,
+        Some("localhost")
+    );
+    assert_eq!(
+        parse_message_with_options(msg, Variant::Either, ParseOptions::default()).hostname,
+        Some("127.0.0.1")
+    );
+
+    let msg = "<34>1 2003-10-11T22:14:15.003Z 127.0.0.2 su - ID47 - msg";
+    assert_eq!(
+        parse_message_with_options(msg, Variant::RFC5424, options).hostname,
+        Some("127.0.0.2")
+    );
+}
+
+#[test]
+fn parse_keep_msg_whitespace() {
+    let options = ParseOptions {
+        keep_msg_whitespace: true,
+        ..Default::default()
+    };
+
+    let msg = "<34>1 2003-10-11T22:14:15.003Z host su - ID47 -    indented message";
+    assert_eq!(
+        parse_message_with_options(msg, Variant::Either, options).msg,
+        "   indented message"
+    );
+    assert_eq!(
+        parse_message_with_options(msg, Variant::Either, ParseOptions::default()).msg,
+        "indented message"
+    );
+
+    let msg = r#"<34>1 2003-10-11T22:14:15.003Z host su - ID47 [meta sequenceId="1"]  indented"#;
+    assert_eq!(
+        parse_message_with_options(msg, Variant::Either, options).msg,
+        " indented"
+    );
+
+    let msg = "<13>Feb 13 20:07:26 host app:   indented message";
+    assert_eq!(
+        parse_message_with_options(msg, Variant::Either, options).msg,
+        "  indented message"
+    );
+    assert_eq!(
+        parse_message_with_options(msg, Variant::Either, ParseOptions::default()).msg,
+        "indented message"
+    );
+}
+
+#[test]
+fn parse_timezone_is_known() {
+    let msg = r#"<165>1 2003-10-11T22:14:15.003Z host app - - [timeQuality tzKnown="0" isSynced="0"] msg"#;
+    assert_eq!(
+        parse_message(msg, Variant::Either).timezone_is_known(),
+        Some(false)
+    );
+
+    let msg = r#"<165>1 2003-10-11T22:14:15.003Z host app - - [timeQuality tzKnown="1"] msg"#;
+    assert_eq!(
+        parse_message(msg, Variant::Either).timezone_is_known(),
+        Some(true)
+    );
+
+    let msg = r#"<165>1 2003-10-11T22:14:15.003Z host app - - [timeQuality isSynced="1"] msg"#;
+    assert_eq!(
+        parse_message(msg, Variant::Either).timezone_is_known(),
+        None
+    );
+
+    let msg = "<165>1 2003-10-11T22:14:15.003Z host app - - - msg";
+    assert_eq!(
+        parse_message(msg, Variant::Either).timezone_is_known(),
+        None
+    );
+}
+
+#[test]
+fn parse_skip_sequence_prefix() {
+    let msg = "999: <34>Oct 11 22:14:15 host app: msg";
+    let options = ParseOptions {
+        skip_sequence_prefix: true,
+        ..Default::default()
+    };
+
+    let parsed = parse_message_with_options(msg, Variant::Either, options);
+    assert_eq!(parsed.facility, Some(SyslogFacility::LOG_AUTH));
+    assert_eq!(parsed.severity, Some(SyslogSeverity::SEV_CRIT));
+    assert_eq!(parsed.hostname, Some("host"));
+    assert_eq!(parsed.appname, Some("app"));
+    assert_eq!(parsed.msg, "msg");
+
+    let parsed = parse_message_with_options(msg, Variant::Either, ParseOptions::default());
+    assert_eq!(parsed.facility, None);
+    assert_eq!(parsed.msg, msg);
+
+    // Without a priority following it, the number is left alone.
+    let msg = "999: a message";
+    let parsed = parse_message_with_options(msg, Variant::Either, options);
+    assert_eq!(parsed.msg, msg);
+}
+
+#[test]
+fn parse_large_pid() {
+    let msg = "<34>1 2003-10-11T22:14:15.003Z host app 99999999999 - - msg";
+    let parsed = parse_message(msg, Variant::RFC5424);
+    assert_eq!(parsed.procid, Some(ProcId::PID(99999999999)));
+    assert!(parsed.procid.unwrap().in_range(i32::MAX as i64..));
+
+    // Too large for an i64, so it is kept as a name.
+    let msg = "<34>1 2003-10-11T22:14:15.003Z host app 99999999999999999999 - - msg";
+    assert_eq!(
+        parse_message(msg, Variant::RFC5424).procid,
+        Some(ProcId::Name("99999999999999999999"))
+    );
+}
+
+#[test]
+fn parse_relay_hostname() {
+    let msg = "<13>Feb 13 20:07:26 192.0.2.1/host app: msg";
+    let parsed = parse_message_with_year(msg, with_year, Variant::Either);
+
+    assert_eq!(parsed.hostname, Some("192.0.2.1/host"));
+    assert_eq!(parsed.appname, Some("app"));
+    assert_eq!(parsed.relay_hostname(), Some("host"));
+    assert_eq!(
+        parsed.relay_ip(),
+        Some(std::net::IpAddr::from([192, 0, 2, 1]))
+    );
+
+    let msg = "<13>Feb 13 20:07:26 host app: msg";
+    let parsed = parse_message_with_year(msg, with_year, Variant::Either);
+    assert_eq!(parsed.relay_hostname(), Some("host"));
+    assert_eq!(parsed.relay_ip(), None);
+}
+
+#[test]
+fn round_trip_5424_empty_and_nil_msg() {
+    let message = |msg| Message {
+        facility: Some(SyslogFacility::LOG_USER),
+        severity: Some(SyslogSeverity::SEV_NOTICE),
+        timestamp: Some(
+            FixedOffset::west_opt(0)
+                .unwrap()
+                .with_ymd_and_hms(2019, 2, 13, 19, 48, 34)
+                .unwrap(),
+        ),
+        raw_timestamp: None,
+        hostname: Some("host"),
+        appname: Some("app"),
+        procid: None,
+        msgid: Some("ID47"),
+        protocol: Protocol::RFC5424(1),
+        structured_data: vec![],
+        unparsed_structured_data: vec![],
+        had_bom: false,
+        msg,
+    };
+
+    let empty = format!("{}", message(""));
+    let nil = format!("{}", message("-"));
+    assert_ne!(empty, nil);
+
+    assert_eq!(parse_message(&empty, Variant::RFC5424), message(""));
+    assert_eq!(parse_message(&nil, Variant::RFC5424), message("-"));
+
+    let with_sd = |msg| Message {
+        structured_data: vec![StructuredElement {
+            id: "meta",
+            params: vec![("x", "1")],
+        }],
+        unparsed_structured_data: vec![],
+        had_bom: false,
+        ..message(msg)
+    };
+
+    let empty = format!("{}", with_sd(""));
+    let nil = format!("{}", with_sd("-"));
+    assert_ne!(empty, nil);
+
+    assert_eq!(parse_message(&empty, Variant::RFC5424), with_sd(""));
+    assert_eq!(parse_message(&nil, Variant::RFC5424), with_sd("-"));
+}
+
+#[test]
+fn parse_require_sd() {
+    let raw = r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [meta sequenceId="1"] i am foobar"#;
+    let parsed = parse_message_require_sd(raw, Variant::Either).unwrap();
+    assert!(parsed.is_structured());
+    assert_eq!(parsed.msg, "i am foobar");
+
+    let raw = r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - - i am foobar"#;
+    assert!(!parse_message(raw, Variant::Either).is_structured());
+    assert_eq!(
+        parse_message_require_sd(raw, Variant::Either),
+        Err("message does not contain any structured data".to_string())
+    );
+}
+
+#[test]
+fn structured_data_owned() {
+    let raw = r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [meta sequenceId="1" sysUpTime="37"][origin ip="192.168.0.1"] i am foobar"#;
+    let parsed = parse_message(raw, Variant::Either);
+    let structured_data = parsed.structured_data_owned();
+    let owned: Message<String> = parsed.into();
+
+    assert_eq!(structured_data, owned.structured_data);
+}
+
+#[test]
+fn parse_max_sd_elements() {
+    let raw = r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [a x="1"][b][c][d][e] i am foobar"#;
+    let options = ParseOptions {
+        max_sd_elements: Some(2),
+        ..Default::default()
+    };
+
+    let parsed = parse_message_with_options(raw, Variant::Either, options);
+    assert_eq!(
+        parsed.structured_data,
+        vec![
+            StructuredElement {
+                id: "a",
+                params: vec![("x", "1")],
+            },
+            StructuredElement {
+                id: "b",
+                params: vec![],
+            },
+        ]
+    );
+    assert_eq!(parsed.msg, "[c][d][e] i am foobar");
+
+    let parsed = parse_message(raw, Variant::Either);
+    assert_eq!(parsed.structured_data.len(), 5);
+    assert_eq!(parsed.msg, "i am foobar");
+}
+
+#[test]
+fn parse_max_sd_elements_zero() {
+    let options = ParseOptions {
+        max_sd_elements: Some(0),
+        ..Default::default()
+    };
+
+    let raw =
+        r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [a x="1"][b] i am foobar"#;
+    let parsed = parse_message_with_options(raw, Variant::Either, options);
+    assert_eq!(parsed.protocol, Protocol::RFC5424(1));
+    assert_eq!(parsed.hostname, Some("74794bfb6795"));
+    assert!(parsed.structured_data.is_empty());
+    assert_eq!(parsed.msg, r#"[a x="1"][b] i am foobar"#);
+
+    let raw = r#"<13>Feb 13 19:48:34 74794bfb6795 root[8449]: [a x="1"] i am foobar"#;
+    let parsed = parse_message_with_options(raw, Variant::Either, options);
+    assert_eq!(parsed.protocol, Protocol::RFC3164);
+    assert_eq!(parsed.appname, Some("root"));
+    assert!(parsed.structured_data.is_empty());
+    assert_eq!(parsed.msg, r#"[a x="1"] i am foobar"#);
+}
+
+#[test]
+fn parse_bytes() {
+    let raw = br#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [meta sequenceId="1"] i am foobar"#;
+    let parsed = parse_message_bytes(raw, Variant::Either);
+    assert!(matches!(parsed.msg, std::borrow::Cow::Borrowed(_)));
+    assert_eq!(parsed.msg, "i am foobar");
+
+    let raw = b"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [meta sequenceId=\"1\xff\"] i am \xfe\xfffoobar";
+    let parsed = parse_message_bytes(raw, Variant::Either);
+    assert_eq!(parsed.hostname.as_deref(), Some("74794bfb6795"));
+    assert_eq!(parsed.appname.as_deref(), Some("root"));
+    assert_eq!(parsed.procid, Some(ProcId::PID(8449)));
+    assert_eq!(parsed.structured_data[0].id, "meta");
+    assert_eq!(
+        parsed.structured_data[0].params,
+        vec![("sequenceId".into(), "1\u{FFFD}".into())]
+    );
+    assert_eq!(parsed.msg, "i am \u{FFFD}\u{FFFD}foobar");
+    assert_eq!(parsed.raw_msg(), b"i am \xfe\xfffoobar");
+}
+
+#[test]
+fn parse_bytes_latin1() {
+    let raw = b"<13>1 2019-02-13T19:48:34+00:00 host app - - [meta id=\"\xe9\"] caf\xe9";
+    let parsed = parse_message_bytes(raw, Variant::Either);
+    assert_eq!(parsed.msg, "caf\u{FFFD}");
+    assert_eq!(parsed.msg_lossy_latin1(), "café");
+
+    let raw = b"<13>Jan  1 00:00:01 host app: \xe9t\xe9";
+    let parsed = parse_message_bytes(raw, Variant::Either);
+    assert_eq!(parsed.appname.as_deref(), Some("app"));
+    assert_eq!(parsed.msg_lossy_latin1(), "été");
+}
+
+#[test]
+fn parse_auditd() {
+    let msg = r#"<85>Jan  1 00:00:01 host audispd: type=EXECVE msg=audit(1609459200.123:456): argc=2 a0="ls" a1="-l""#;
+    let parsed = parse_message_with_year(msg, |_| 2021, Variant::Either);
+    let record = parsed.parse_auditd().unwrap();
+
+    assert_eq!(record.record_type, "EXECVE");
+    assert_eq!(record.timestamp.timestamp_millis(), 1_609_459_200_123);
+    assert_eq!(record.serial, 456);
+    assert_eq!(
+        record.fields,
+        vec![("argc", "2"), ("a0", "ls"), ("a1", "-l")]
+    );
+}
+
+#[test]
+fn round_trip_5424_subsecond_precision() {
+    for (timestamp, nanos) in [
+        ("2003-10-11T22:14:15.123456789Z", 123_456_789),
+        ("2003-10-11T22:14:15.250000+01:00", 250_000_000),
+        ("2003-10-11T22:14:15.000001Z", 1_000),
+    ] {
+        let msg = format!("<34>1 {} host app - - - message", timestamp);
+        let parsed = parse_message(&msg, Variant::RFC5424);
+        assert_eq!(parsed.timestamp.unwrap().nanosecond(), nanos);
+
+        let displayed = parsed.to_string();
+        assert_eq!(
+            parse_message(&displayed, Variant::RFC5424).timestamp,
+            parsed.timestamp
+        );
+    }
+
+    let msg = "<34>1 2003-10-11T22:14:15.123456789Z host app - - - message";
+    assert!(parse_message(msg, Variant::RFC5424)
+        .to_string()
+        .contains("2003-10-11T22:14:15.123456789+00:00"));
+}
+
+#[test]
+fn parse_with_limits() {
+    let msg = "<34>1 2003-10-11T22:14:15.003Z host app - - - message";
+
+    assert_eq!(
+        parse_message_with_limits(msg, Variant::Either, None),
+        parse_message(msg, Variant::Either)
+    );
+    assert_eq!(
+        parse_message_with_limits(msg, Variant::Either, Some(msg.len())),
+        parse_message(msg, Variant::Either)
+    );
+
+    let parsed = parse_message_with_limits(msg, Variant::Either, Some(10));
+    assert_eq!(parsed.hostname, None);
+    assert_eq!(parsed.timestamp, None);
+    assert_eq!(parsed.msg, msg);
+}
+
+#[test]
+fn filter_by_pid_range() {
+    let messages = [
+        "<34>1 2003-10-11T22:14:15.003Z host app 100 - - message",
+        "<34>1 2003-10-11T22:14:15.003Z host app 2000 - - message",
+        "<34>1 2003-10-11T22:14:15.003Z host app worker - - message",
+        "<34>1 2003-10-11T22:14:15.003Z host app - - - message",
+    ]
+    .iter()
+    .map(|msg| parse_message(msg, Variant::Either))
+    .collect::<Vec<_>>();
+
+    let pids = messages
+        .iter()
+        .filter(|msg| {
+            msg.procid
+                .as_ref()
+                .is_some_and(|procid| procid.in_range(1..1000))
+        })
+        .map(|msg| msg.procid.as_ref().and_then(|procid| procid.pid()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(pids, vec![Some(100)]);
+    assert_eq!(messages[2].procid.as_ref().unwrap().pid(), None);
+}
+
+#[test]
+fn parse_year_with_tz_context() {
+    // It is still 2019 in New York when it is 2020 in UTC.
+    let now = Utc.with_ymd_and_hms(2020, 1, 1, 2, 0, 0).unwrap();
+    let tz = FixedOffset::west_opt(5 * 3600).unwrap();
+    let raw = "<34>Dec 31 21:00:00 host app: message";
+
+    let parsed = parse_message_with_year_tz_ctx(
+        raw,
+        |_, tz: Option<FixedOffset>| now.with_timezone(&tz.unwrap()).year(),
+        Some(tz),
+        Variant::RFC3164,
+    );
+
+    assert_eq!(
+        parsed.timestamp,
+        Some(tz.with_ymd_and_hms(2019, 12, 31, 21, 0, 0).unwrap())
+    );
+}
+
+#[test]
+fn parse_single_quoted_sd_values() {
+    let msg = r#"<34>1 2003-10-11T22:14:15.003Z host app - - [x y='hello world' z='it\'s' q="quoted"] msg"#;
+    let options = ParseOptions {
+        single_quoted_sd_values: true,
+        ..Default::default()
+    };
+
+    let parsed = parse_message_with_options(msg, Variant::Either, options);
+    assert_eq!(parsed.structured_data.len(), 1);
+    let element = &parsed.structured_data[0];
+    assert_eq!(element.get("y"), Some("hello world".to_string()));
+    assert_eq!(element.get("z"), Some(r"it\'s".to_string()));
+    assert_eq!(element.get("q"), Some("quoted".to_string()));
+    assert_eq!(parsed.msg, "msg");
+
+    // By default the element is invalid, so is kept in the unparsed structured data.
+    let parsed = parse_message_with_options(msg, Variant::Either, ParseOptions::default());
+    assert!(parsed.structured_data.is_empty());
+    assert_eq!(
+        parsed.unparsed_structured_data,
+        vec![r#"[x y='hello world' z='it\'s' q="quoted"]"#]
+    );
+}
+
+#[test]
+fn parse_metadata() {
+    let (message, metadata) =
+        parse_message_with_metadata("Oct 11 22:14:15 host app: msg", Variant::Either);
+    assert_eq!(message.msg, "msg");
+    assert_eq!(
+        metadata,
+        ParseMetadata {
+            variant: Variant::RFC3164,
+            pri_present: false,
+            fallback: false,
+            framing: Framing::None,
+        }
+    );
+
+    let msg = "<34>1 2003-10-11T22:14:15.003Z host app - - - msg";
+    let framed = format!("{} {}", msg.len(), msg);
+    let (message, metadata) = parse_message_with_metadata(&framed, Variant::Either);
+    assert_eq!(message.msg, "msg");
+    assert_eq!(
+        metadata,
+        ParseMetadata {
+            variant: Variant::RFC5424,
+            pri_present: true,
+            fallback: false,
+            framing: Framing::OctetCounting,
+        }
+    );
+
+    let (message, metadata) = parse_message_with_metadata("not syslog\n", Variant::RFC5424);
+    assert_eq!(message.msg, "not syslog");
+    assert_eq!(
+        metadata,
+        ParseMetadata {
+            variant: Variant::RFC3164,
+            pri_present: false,
+            fallback: true,
+            framing: Framing::NonTransparent,
+        }
+    );
+}
+
+#[test]
+fn parse_strict_rfc3164_timestamp() {
+    let msg = "<34>2020-10-11T22:14:15Z host app: msg";
+
+    let parsed = parse_message_with_options(msg, Variant::RFC3164, ParseOptions::default());
+    assert_eq!(
+        parsed.timestamp,
+        Some(
+            Utc.fix()
+                .with_ymd_and_hms(2020, 10, 11, 22, 14, 15)
+                .unwrap()
+        )
+    );
+    assert_eq!(parsed.hostname, Some("host"));
+    assert_eq!(parsed.msg, "msg");
+
+    let options = ParseOptions {
+        strict_rfc3164_timestamp: true,
+        ..Default::default()
+    };
+    let parsed = parse_message_with_options(msg, Variant::RFC3164, options);
+    assert_eq!(parsed.timestamp, None);
+    assert_eq!(parsed.hostname, None);
+    assert_eq!(parsed.msg, msg);
+}
+
+#[test]
+fn parse_5424_json_without_structured_data() {
+    let msg = r#"<134>1 2020-10-30T16:05:45Z host app - - {"user":"admin","nested":{"ok":true}}"#;
+
+    let parsed = parse_message(msg, Variant::Either);
+    assert_eq!(parsed.protocol, Protocol::RFC5424(1));
+    assert_eq!(parsed.hostname, Some("host"));
+    assert_eq!(parsed.appname, Some("app"));
+    assert!(parsed.structured_data.is_empty());
+    assert_eq!(parsed.msg, r#"{"user":"admin","nested":{"ok":true}}"#);
+}
+
+#[test]
+fn parse_5424_bad_structured_data_not_missing_hostname() {
+    let msg = "<34>1 2003-10-11T22:14:15Z host app - - [bad";
+    let parsed = parse_message(msg, Variant::RFC5424);
+    assert_eq!(parsed.hostname, None);
+    assert_eq!(parsed.appname, None);
+    assert_eq!(parsed.procid, None);
+    assert_eq!(parsed.msg, msg);
+}
+
+#[test]
+fn parse_5424_missing_hostname_multi_word_body() {
+    let parsed = parse_message(
+        "<34>1 2003-10-11T22:14:15Z app - - hello world",
+        Variant::Either,
+    );
+    assert_eq!(parsed.protocol, Protocol::RFC5424(1));
+    assert_eq!(parsed.hostname, None);
+    assert_eq!(parsed.appname, Some("app"));
+    assert_eq!(parsed.msgid, None);
+    assert_eq!(parsed.msg, "hello world");
+}
+
+#[test]
+fn parse_exact_detailed_error() {
+    let msg = "<34>1 not-a-timestamp host app - - - msg";
+    let err: ParseError =
+        parse_message_with_year_exact_detailed(msg, with_year, Variant::RFC5424).unwrap_err();
+    assert_eq!(err.input(), Some("not-a-timestamp host app - - - msg"));
+    assert_eq!(err.offset(msg), Some(6));
+
+    let msg = "<34>1 2003-10-11T22:14:15.003Z host app - - - msg";
+    let parsed = parse_message_with_year_exact_detailed(msg, with_year, Variant::RFC5424).unwrap();
+    assert_eq!(parsed.msg, "msg");
+}
+
+#[test]
+fn parse_numeric_month() {
+    let msg = "<34>01 05 15:33:03 host app: msg";
+    let options = ParseOptions {
+        numeric_month: true,
+        ..Default::default()
+    };
+
+    let parsed = parse_message_with_year_tz_options(
+        msg,
+        |_| 2020,
+        Some(Utc.fix()),
+        Variant::RFC3164,
+        options,
+    );
+    assert_eq!(
+        parsed.timestamp,
+        Some(Utc.fix().with_ymd_and_hms(2020, 1, 5, 15, 33, 3).unwrap())
+    );
+    assert_eq!(parsed.hostname, Some("host"));
+    assert_eq!(parsed.appname, Some("app"));
+    assert_eq!(parsed.msg, "msg");
+}
+
+#[test]
+fn parse_structured_data_nil_value() {
+    let parsed = parse_message(
+        "<34>1 2003-10-11T22:14:15.003Z host app - - -",
+        Variant::Either,
+    );
+    assert_eq!(parsed.protocol, Protocol::RFC5424(1));
+    assert_eq!(parsed.procid, None);
+    assert_eq!(parsed.msgid, None);
+    assert!(parsed.structured_data.is_empty());
+    assert_eq!(parsed.msg, "");
+
+    // In RFC3164 a `-` on its own is the message.
+    let parsed = parse_message("<34>Oct 11 22:14:15 host app: -", Variant::RFC3164);
+    assert!(parsed.structured_data.is_empty());
+    assert_eq!(parsed.msg, "-");
+
+    let parsed = parse_message("<34>Oct 11 22:14:15 host app: -v", Variant::RFC3164);
+    assert_eq!(parsed.msg, "-v");
+
+    let parsed = parse_message("<34>Oct 11 22:14:15 host app: - msg", Variant::RFC3164);
+    assert_eq!(parsed.msg, "msg");
+}
+
+#[test]
+fn parse_if_severity() {
+    let important = |_, severity: Option<SyslogSeverity>| {
+        severity.is_none_or(|severity| severity <= SyslogSeverity::SEV_WARNING)
+    };
+
+    let parsed = parse_message_if(
+        "<34>1 2003-10-11T22:14:15.003Z host app - - - crit",
+        Variant::Either,
+        important,
+    )
+    .unwrap();
+    assert_eq!(parsed.severity, Some(SyslogSeverity::SEV_CRIT));
+    assert_eq!(parsed.msg, "crit");
+
+    assert_eq!(
+        parse_message_if(
+            "<39>1 2003-10-11T22:14:15.003Z host app - - - debug",
+            Variant::Either,
+            important,
+        ),
+        None
+    );
+
+    // Messages without a priority are passed to the predicate with no facility or severity.
+    assert!(parse_message_if("a message", Variant::Either, important).is_some());
+}
+
+#[test]
+fn parse_strict_sd_ids() {
+    let msg = r#"<34>1 2003-10-11T22:14:15.003Z host app - - [exampleSDID@32473@1 iut="3"][exampleSDID@32473 iut="3"] msg"#;
+    let options = ParseOptions {
+        strict_sd_ids: true,
+        ..Default::default()
+    };
+
+    let parsed = parse_message_with_options(msg, Variant::RFC5424, options);
+    assert_eq!(
+        parsed.structured_data,
+        vec![StructuredElement {
+            id: "exampleSDID@32473",
+            params: vec![("iut", "3")],
+        }]
+    );
+    assert_eq!(
+        parsed.unparsed_structured_data,
+        vec![r#"[exampleSDID@32473@1 iut="3"]"#]
+    );
+    assert_eq!(parsed.msg, "msg");
+
+    let parsed = parse_message_with_options(msg, Variant::RFC5424, ParseOptions::default());
+    assert_eq!(parsed.structured_data.len(), 2);
+    assert!(parsed.unparsed_structured_data.is_empty());
+}
+
+#[test]
+fn parse_sd_value_with_literal_newline() {
+    let msg = "<34>1 2003-10-11T22:14:15.003Z host app - - [meta detail=\"line1\nline2\" end=\"\\\"quoted\\\"\nnext\"] msg";
+
+    let parsed = parse_message(msg, Variant::RFC5424);
+    assert_eq!(
+        parsed.structured_data,
+        vec![StructuredElement {
+            id: "meta",
+            params: vec![("detail", "line1\nline2"), ("end", "\\\"quoted\\\"\nnext")],
+        }]
+    );
+    assert_eq!(
+        parsed.structured_data[0].get("end"),
+        Some("\"quoted\"\nnext".to_string())
+    );
+    assert_eq!(parsed.msg, "msg");
+}
+
+#[test]
+fn parse_with_combined_options() {
+    let options = ParseOptions {
+        variant: Variant::RFC3164,
+        timezone: Some(FixedOffset::east_opt(3600).unwrap()),
+        get_year: Some(|_| 2020),
+        require_pri: true,
+        hex_pri: true,
+        normalize_localhost: true,
+        max_len: Some(64),
+        ..Default::default()
+    };
+
+    let parsed = parse_with_options("<0x22>Oct 11 22:14:15 127.0.0.1 app: msg", &options).unwrap();
+    assert_eq!(parsed.facility, Some(SyslogFacility::LOG_AUTH));
+    assert_eq!(parsed.severity, Some(SyslogSeverity::SEV_CRIT));
+    assert_eq!(
+        parsed.timestamp,
+        Some(
+            FixedOffset::east_opt(3600)
+                .unwrap()
+                .with_ymd_and_hms(2020, 10, 11, 22, 14, 15)
+                .unwrap()
+        )
+    );
+    assert_eq!(parsed.hostname, Some("localhost"));
+    assert_eq!(parsed.msg, "msg");
+
+    let too_long = format!("<34>Oct 11 22:14:15 host app: {}", "x".repeat(64));
+    let err = parse_with_options(&too_long, &options).unwrap_err();
+    assert_eq!(err.offset(&too_long), Some(0));
+
+    let options = ParseOptions {
+        variant: Variant::RFC5424,
+        require_pri: true,
+        single_quoted_sd_values: true,
+        max_sd_elements: Some(1),
+        ..Default::default()
+    };
+
+    let parsed = parse_with_options(
+        "<34>1 2003-10-11T22:14:15.003Z host app - - [a x='1'][b y=\"2\"] msg",
+        &options,
+    )
+    .unwrap();
+    assert_eq!(
+        parsed.structured_data,
+        vec![StructuredElement {
+            id: "a",
+            params: vec![("x", "1")],
+        }]
+    );
+    assert_eq!(parsed.msg, "[b y=\"2\"] msg");
+
+    assert!(parse_with_options("1 2003-10-11T22:14:15.003Z host app - - - msg", &options).is_err());
+}
+
+#[test]
+fn parse_with_options_lowercase_hostname() {
+    let msg = "<34>1 2003-10-11T22:14:15.003Z MyMachine su - ID47 - msg";
+    let options = ParseOptions {
+        lowercase_hostname: true,
+        ..Default::default()
+    };
+
+    // The borrowed message can't hold a lowercased hostname, so it is left as it is.
+    let parsed = parse_with_options(msg, &options).unwrap();
+    assert_eq!(parsed.hostname, Some("MyMachine"));
+
+    let parsed = parse_message_with_options_owned(msg, Variant::Either, options);
+    assert_eq!(parsed.hostname.as_deref(), Some("mymachine"));
+}
+
+#[test]
+fn parse_fqdn_trailing_dot() {
+    let msg = "<34>1 2003-10-11T22:14:15.003Z mymachine.example.com. su - ID47 - msg";
+
+    // The trailing dot is kept by default.
+    let parsed = parse_message(msg, Variant::RFC5424);
+    assert_eq!(parsed.hostname, Some("mymachine.example.com."));
+    assert_eq!(parsed.appname, Some("su"));
+
+    let options = ParseOptions {
+        trim_hostname_dot: true,
+        ..Default::default()
+    };
+    let parsed = parse_message_with_options(msg, Variant::RFC5424, options);
+    assert_eq!(parsed.hostname, Some("mymachine.example.com"));
+
+    let parsed = parse_message_with_options(
+        "<34>Oct 11 22:14:15 mymachine.example.com. su: msg",
+        Variant::RFC3164,
+        options,
+    );
+    assert_eq!(parsed.hostname, Some("mymachine.example.com"));
+    assert_eq!(parsed.appname, Some("su"));
+}
+
+#[test]
+fn parse_with_timezone() {
+    let parsed = parse_message(
+        "<34>1 2020-07-01T12:00:00+02:00 host app - - - msg",
+        Variant::RFC5424,
+    )
+    .with_timezone(chrono_tz::America::New_York);
+
+    assert_eq!(
+        parsed.timestamp.unwrap().to_rfc3339(),
+        "2020-07-01T06:00:00-04:00"
+    );
+}
+
+#[test]
+fn parse_repeated_count() {
+    let parsed = parse_message(
+        "<34>Oct 11 22:14:15 host last message repeated 3 times",
+        Variant::RFC3164,
+    );
+    assert_eq!(parsed.hostname, Some("host"));
+    assert_eq!(parsed.repeated_count(), Some(3));
+}
+
+#[test]
+fn parse_malformed_without_panicking() {
+    let inputs = [
+        "",
+        "<",
+        "<>",
+        "<34>",
+        "<34",
+        "<999999999999999999999>",
+        "<0x>",
+        "[",
+        "]",
+        "\u{feff}",
+        "<34>1",
+        "<34>1 ",
+        "<34>1 -",
+        "<34>1 2003-10-11T22:14:15.003Z",
+        "<34>1 2003-10-11T22:14:15.003Z host app - - [",
+        "<34>1 2003-10-11T22:14:15.003Z host app - - [x y=\"",
+        "<34>1 2003-10-11T22:14:15.003Z host app - - [x y=\"\\",
+        "<34>1 2003-10-11T22:14:60.003Z host app - - - msg",
+        "<34>1 +262143-12-31T23:59:59Z host app - - - msg",
+        "<34>Feb 29 00:00:00 host app: msg",
+        "<34>Feb 30 00:00:00 host app: msg",
+        "<34>Oct 11 25:61:61 host app: msg",
+        "<34>Oct 11 22:14:15.9999999999999 host app: msg",
+        "<34>Oct 11 99999999999 22:14:15 host app: msg",
+        "<34>Oct 11 22:14:15 99999 host app[99999999999999999999]: msg",
+        "<34>[9999-99-99 99:99:99] msg",
+        "12: <34>",
+        "é<34>é Oct é",
+    ];
+
+    for input in inputs.iter() {
+        for variant in [Variant::Either, Variant::RFC3164, Variant::RFC5424].iter() {
+            let message = parse_message_with_year(input, |_| 2021, *variant);
+            let _ = message.to_string();
+        }
+    }
+}
+
+#[test]
+fn parse_bare_pri() {
+    let options = ParseOptions {
+        bare_pri: true,
+        ..Default::default()
+    };
+
+    for msg in [
+        "<34>Oct 11 22:14:15 host app: msg",
+        "34 Oct 11 22:14:15 host app: msg",
+    ]
+    .iter()
+    {
+        let parsed = parse_message_with_options(msg, Variant::RFC3164, options);
+        assert_eq!(parsed.facility, Some(SyslogFacility::LOG_AUTH), "{}", msg);
+        assert_eq!(parsed.severity, Some(SyslogSeverity::SEV_CRIT), "{}", msg);
+        assert_eq!(parsed.hostname, Some("host"), "{}", msg);
+        assert_eq!(parsed.appname, Some("app"), "{}", msg);
+        assert_eq!(parsed.msg, "msg", "{}", msg);
+    }
+
+    // By default the number is not a priority.
+    let msg = "34 Oct 11 22:14:15 host app: msg";
+    let parsed = parse_message_with_options(msg, Variant::RFC3164, ParseOptions::default());
+    assert_eq!(parsed.facility, None);
+    assert_eq!(parsed.severity, None);
+}
+
+#[test]
+fn rsyslog_omfwd_tcp_forward_format_round_trip() {
+    let raw = r#"<190>2019-02-13T21:53:30.605850+00:00 74794bfb6795 liblogging-stdlog:  [origin software="rsyslogd" swVersion="8.24.0" x-pid="9043" x-info="http://www.rsyslog.com"] start"#;
+
+    let parsed = parse_message_with_year(raw, with_year, Variant::Either);
+    let displayed = parsed.to_string();
+    assert_eq!(
+        displayed,
+        r#"<190> 2019-02-13T21:53:30.605850+00:00 74794bfb6795 liblogging-stdlog: [origin software="rsyslogd" swVersion="8.24.0" x-pid="9043" x-info="http://www.rsyslog.com"] start"#
+    );
+
+    let reparsed = parse_message_with_year(&displayed, with_year, Variant::Either);
+    assert_eq!(reparsed.protocol, Protocol::RFC3164);
+    assert_eq!(reparsed, parsed);
+    assert_eq!(
+        reparsed.raw_timestamp,
+        Some("2019-02-13T21:53:30.605850+00:00")
+    );
+}
+
+#[test]
+fn parse_owned() {
+    fn parse_and_drop_input(variant: Variant) -> Message<String> {
+        let input = String::from("<34>Oct 11 22:14:15 host app: msg");
+        parse_message_owned(&input, variant)
+    }
+
+    let parsed = parse_and_drop_input(Variant::Either);
+    assert_eq!(parsed.hostname.as_deref(), Some("host"));
+    assert_eq!(parsed.msg, "msg");
+
+    let raw = "<34>Oct 11 22:14:15 host app: msg";
+    let expected: Message<String> = parse_message_with_year(raw, with_year, Variant::Either).into();
+    assert_eq!(
+        parse_message_with_year_owned(raw, with_year, Variant::Either),
+        expected
+    );
+
+    let expected: Message<String> =
+        parse_message_with_year_tz(raw, with_year, Some(Utc.fix()), Variant::Either).into();
+    assert_eq!(
+        parse_message_with_year_tz_owned(raw, with_year, Some(Utc.fix()), Variant::Either),
+        expected
+    );
+    assert_eq!(
+        expected.timestamp,
+        Some(
+            Utc.fix()
+                .with_ymd_and_hms(2020, 10, 11, 22, 14, 15)
+                .unwrap()
+        )
+    );
+}
+
+#[test]
+fn parse_origin() {
+    let raw = format!(
+        r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - {}{} {}"#,
+        r#"[meta sequenceId="1" sysUpTime="37" language="EN"]"#,
+        r#"[origin ip="192.168.0.1" software="test"]"#,
+        "i am foobar"
+    );
+
+    assert_eq!(
+        parse_message(&raw, Variant::Either).origin(),
+        Some(Origin {
+            ip: Some("192.168.0.1".to_string()),
+            enterprise_id: None,
+            software: Some("test".to_string()),
+            sw_version: None,
+        })
+    );
+
+    let raw = r#"<13>1 2019-02-13T19:48:34+00:00 host root 8449 - [origin enterpriseId="32473.1" swVersion="8.24.0"] msg"#;
+    let origin = parse_message(raw, Variant::Either).origin().unwrap();
+    assert_eq!(origin.enterprise_id.as_deref(), Some("32473.1"));
+    assert_eq!(origin.sw_version.as_deref(), Some("8.24.0"));
+    assert_eq!(origin.ip, None);
+
+    let raw = r#"<13>1 2019-02-13T19:48:34+00:00 host root 8449 - [meta sequenceId="1"] msg"#;
+    assert_eq!(parse_message(raw, Variant::Either).origin(), None);
+}
+
+#[test]
+fn parse_mixed_quoted_sd_values() {
+    let msg = r#"<34>1 2003-10-11T22:14:15.003Z host app - - [meta id='abc'][x a="1" b='2'] msg"#;
+    let options = ParseOptions {
+        single_quoted_sd_values: true,
+        ..Default::default()
+    };
+
+    let parsed = parse_message_with_options(msg, Variant::RFC5424, options);
+    assert_eq!(
+        parsed.structured_data,
+        vec![
+            StructuredElement {
+                id: "meta",
+                params: vec![("id", "abc")],
+            },
+            StructuredElement {
+                id: "x",
+                params: vec![("a", "1"), ("b", "2")],
+            },
+        ]
+    );
+    assert_eq!(parsed.msg, "msg");
+
+    let parsed = parse_message_with_options(msg, Variant::RFC5424, ParseOptions::default());
+    assert!(parsed.structured_data.is_empty());
+    assert_eq!(parsed.unparsed_structured_data.len(), 2);
+}